@@ -1,35 +1,127 @@
-use std::{collections::{hash_map::Entry, BTreeMap, HashMap}, path::PathBuf};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, path::PathBuf};
 
-use egui::{FontData, FontDefinitions, FontFamily, Pos2, Rect};
+use egui::{FontData, FontDefinitions, FontFamily, Pos2, Rect, Vec2};
 use egui_extras::install_image_loaders;
-use itertools::Itertools;
 use poll_promise::Promise;
 use rand::random_range;
-use rfd::FileHandle;
 use turingrs::{parser::parse_turing_machine, turing_machine::{TuringExecutionStep, TuringMachine, TuringMachineExecutor}, turing_state::{TuringDirection, TuringTransition}};
 
-use crate::ui::{self, constant::Constant, turing::{State, Transition}};
+use crate::ui::{self, constant::Constant, graph::EdgeCache, transition_editor::TransitionEditor, turing::{State, Transition}};
+
+use declaration::Declarations;
+pub use diagnostic::Diagnostic;
+use history::History;
+use layout::LayoutState;
+use sync::SyncState;
+use undo::UndoStack;
+
+mod clipboard;
+mod declaration;
+mod diagnostic;
+mod dot;
+mod edit;
+mod generate;
+mod history;
+pub mod jflap;
+pub mod layout;
+pub mod machine;
+pub mod project;
+pub mod sync;
+mod tikz;
+mod undo;
+pub mod xml;
 
 
 /// The application data, not refresh after each draw
 pub struct TuringApp {
     pub turing: TuringMachineExecutor,
-    pub current_step: TuringExecutionStep,
+    pub history: History,
     pub word_input: String,
-    pub count: u8,
     pub is_accepted: Option<bool>,
+    pub playback: Playback,
     pub graph_rect: Rect,
+    pub layout: LayoutState,
     pub states: HashMap<u8, State>,
+    /// The curves and labels of the graph, computed again when it changes
+    pub edge_cache: EdgeCache,
     pub selection: Selection,
+    pub transition_editor: Option<TransitionEditor>,
     pub code: String,
+    pub diagnostic: Option<Diagnostic>,
+    pub sync: SyncState,
+    pub undo: UndoStack,
+    pub file_path: Option<PathBuf>,
+    pub is_dirty: bool,
     pub promise: Option<Promise<Option<PathBuf>>>,
-    pub promise_wasm: Option<Promise<Option<FileHandle>>>
+    /// The name and the content of the file being opened in the browser, once read
+    pub promise_wasm: Option<Promise<Option<(PathBuf, String)>>>,
+    pub save_promise: Option<Promise<Option<Result<PathBuf, String>>>>,
+    /// The export being saved, while the file dialog is opened
+    pub export_promise: Option<Promise<Result<(), String>>>,
+    /// The content of the file being imported, once read
+    pub import_promise: Option<Promise<Option<Result<String, String>>>>,
 }
 
 #[derive(Default)]
 pub struct Selection {
     pub selected_transition: Option<(u8, u8)>,
-    pub selected_state: Option<u8>
+    pub selected_states: BTreeSet<u8>,
+    pub is_editing: bool,
+    /// The name typed while renaming the selected state
+    pub name_input: String,
+    /// The reason the name typed can't be used
+    pub rename_error: Option<String>,
+    /// Where the box selection started, in the coordinates of the graph
+    pub box_start: Option<Pos2>,
+}
+
+impl Selection {
+    /// Select only one state
+    pub fn state(id: u8) -> Self {
+        Selection {
+            selected_states: BTreeSet::from([id]),
+            ..Default::default()
+        }
+    }
+
+    /// Select a state and start renaming it
+    pub fn rename_state(id: u8, name: &str) -> Self {
+        Selection {
+            is_editing: true,
+            name_input: name.to_string(),
+            ..Selection::state(id)
+        }
+    }
+
+    /// The state selected if it is the only one
+    pub fn single_state(&self) -> Option<u8> {
+        match self.selected_states.len() {
+            1 => self.selected_states.first().copied(),
+            _ => None,
+        }
+    }
+}
+
+/// State of the automatic execution started by the play button
+pub struct Playback {
+    pub is_running: bool,
+    pub speed: f32,
+    pub is_max_speed: bool,
+    pub last_step: Option<f64>,
+    /// The step the execution is going to, computed over several frames
+    pub target: Option<usize>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            is_running: false,
+            speed: Constant::DEFAULT_SPEED,
+            is_max_speed: false,
+            last_step: None,
+            target: None,
+        }
+    }
 }
 
 /// Default implementation of TuringApp
@@ -54,7 +146,7 @@ impl Default for TuringApp {
             id: t1,
             parent_id: 0,
             text: turing_machine.states[0].transitions[t1 as usize].to_string(),
-            target_id: 1
+            target_id: 1,
         });
 
         let (turing_executor, initial_turing_step) = TuringMachineExecutor::new(
@@ -65,16 +157,27 @@ impl Default for TuringApp {
         // Implement the TuringApp
         Self {
             turing: turing_executor,
-            current_step : initial_turing_step,
+            history: History::new(initial_turing_step, 0),
             word_input: "".to_string(),
-            count: 0,
             is_accepted: None,
+            playback: Playback::default(),
             graph_rect: Rect::ZERO,
+            layout: LayoutState::default(),
             states: states,
+            edge_cache: EdgeCache::default(),
             selection: Selection::default(),
+            transition_editor: None,
             code: "".to_string(),
+            diagnostic: None,
+            sync: SyncState::default(),
+            undo: UndoStack::default(),
+            file_path: None,
+            is_dirty: false,
             promise: None,
             promise_wasm: None,
+            save_promise: None,
+            export_promise: None,
+            import_promise: None,
         }
     }
 
@@ -92,63 +195,86 @@ impl TuringApp {
 
         load_font(cc);
 
-        Default::default()
+        let mut app = TuringApp::default();
+
+        // resume the previous session, saved natively in a file and in the local storage on the web
+        if let Some(session) = cc.storage.and_then(|storage| eframe::get_value(storage, eframe::APP_KEY)) {
+            app.load_session(session);
+        }
+        app.reset_undo();
+
+        app
     }
 
-    pub fn compile(&mut self) {
+    /// The name of the file being edited
+    pub fn file_name(&self) -> String {
+        self.file_path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or("untitled.tm".to_string())
+    }
 
-        let new_turing_machine = parse_turing_machine(self.code.to_string()).unwrap();
-        (self.turing, self.current_step) = TuringMachineExecutor::new(new_turing_machine, self.word_input.to_string()).unwrap();
-        self.code_to_graph();
+    /// The title of the window, the file name is followed by a star if there are unsaved changes
+    pub fn title(&self) -> String {
+        format!("{}{} - Turingrs", self.file_name(), if self.is_dirty {"*"} else {""})
     }
 
-    pub fn graph_to_code(&mut self) {
+    /// Compile the code into a new turing machine, return false if it can't be compiled.
+    /// If the code is invalid, the error is kept to be displayed and the previous machine keep running.
+    pub fn compile(&mut self) -> bool {
 
-        let mut final_code = "".to_string();
-        
-        let mut transitions_hashmap: HashMap<(String, String), Vec<&Transition>> = HashMap::new();
-
-        // iterate all states to find transitions
-        for (index, state) in self.states.iter() {
-
-            // each state hold all transitions of which its the source
-            for transition in state.transitions.iter() {
-
-                // get TuringTransition to acquire the nex state id
-                let target_state_name = &self.turing.turing_machine
-                    .states[transition.target_id as usize].name;
-
-                // if the value exist, add to the vector, if not create a new pair key/value
-                match transitions_hashmap.entry((state.name.clone(), target_state_name.to_string())) {
-                    Entry::Occupied(mut e) => {
-                        e.get_mut().push(transition);
-                    }
-                    Entry::Vacant(e) => {
-                        e.insert(vec![transition]);
-                    }
-                }
+        let new_turing_machine = match parse_turing_machine(self.code.to_string()) {
+            Ok(mut turing_machine) => {
+                Declarations::parse(&self.code).apply(&mut turing_machine);
+                turing_machine
             }
-        }
+            Err(error) => {
+                self.diagnostic = Some(Diagnostic::new(error.to_string()));
+                return false;
+            }
+        };
 
-        final_code = transitions_hashmap.iter()
-            .map(|(i,ts)| {
-                format!("q_{} {{{}}} q_{};",
-                i.0,
-                ts.iter().map(|f| &f.text).join("\n | "),
-                i.1)
-            }).join("\n\n");
+        if !self.execute(new_turing_machine) {
+            return false;
+        }
 
-        self.code = final_code;
-        
+        self.diagnostic = None;
+        self.code_to_graph();
+        true
     }
 
     pub fn update(&mut self) {
-        (self.turing, self.current_step) = TuringMachineExecutor::new(self.turing.turing_machine.to_owned(), self.word_input.to_string()).unwrap();
+        self.execute(self.turing.turing_machine.to_owned());
     }
 
+    /// Restart the execution from the input word with a new executor, the history is cleared.
+    /// Return false and keep the previous executor if the executor can't be created.
+    fn execute(&mut self, turing_machine: TuringMachine) -> bool {
+        let initial_step: TuringExecutionStep;
+        (self.turing, initial_step) = match TuringMachineExecutor::new(turing_machine, self.word_input.to_string()) {
+            Ok(executor) => executor,
+            Err(error) => {
+                self.diagnostic = Some(Diagnostic::new(format!("{:?}", error)));
+                return false;
+            }
+        };
+        self.history = History::new(initial_step, self.turing.get_state_pointer());
+        self.is_accepted = None;
+        self.playback.is_running = false;
+        self.playback.target = None;
+        true
+    }
+
+    /// Rebuild the graph from the turing machine.
+    /// The states keep the position, color and curves of the previous states with the same name.
+    /// The transition editor is closed and the layout animation stopped as the indexes may have changed.
     pub fn code_to_graph(&mut self) {
 
-        self.states = HashMap::new();
+        let previous_states = std::mem::take(&mut self.states);
+        self.transition_editor = None;
+        self.layout.stop();
+        self.layout.reheat();
 
         for (state_id, state) in self.turing.turing_machine.states.iter_mut().enumerate() {
 
@@ -158,16 +284,23 @@ impl TuringApp {
                     text: transition.to_string(),
                     id: transition_id as u8,
                     parent_id: state_id as u8,
-                    target_id: transition.index_to_state
+                    target_id: transition.index_to_state,
                 });
             }
 
+            let previous_state = previous_states.values().find(|previous| previous.name == state.name);
+
             self.states.insert(state_id as u8, State {
                 name: state.name.to_string(),
-                position: Pos2::new(random_range(-100.0..100.0), random_range(-100.0..100.0)),
+                position: previous_state
+                    .map(|previous| previous.position)
+                    .unwrap_or_else(|| Pos2::new(random_range(-100.0..100.0), random_range(-100.0..100.0))),
                 id: state_id as u8,
-                color: Constant::PRIMARY_COLOR,
+                color: previous_state.map(|previous| previous.color).unwrap_or(Constant::PRIMARY_COLOR),
                 transitions: transitions,
+                velocity: Vec2::ZERO,
+                is_pinned: false,
+                curves: previous_state.map(|previous| previous.curves.clone()).unwrap_or_default(),
             });
         }
     }
@@ -180,6 +313,22 @@ impl eframe::App for TuringApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         install_image_loaders(ctx);
         ui::show(self, ctx);
+
+        // propagate the edits between the code and the graph, waiting for the user to stop editing
+        if let Some(delay) = self.synchronize(ctx.input(|input| input.time)) {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(delay));
+        }
+
+        // update the window title only when it change
+        let title = self.title();
+        if ctx.input(|input| input.viewport().title.as_ref() != Some(&title)) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title));
+        }
+    }
+
+    /// Save the session to resume it on the next launch
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.to_session());
     }
 }
 
@@ -217,3 +366,13 @@ fn load_font(cc: &eframe::CreationContext<'_>) {
 
     cc.egui_ctx.set_fonts(fonts);
 }
+
+#[cfg(test)]
+impl TuringApp {
+    /// An application with the code compiled, the tests fail if it can't be compiled
+    pub fn compiled(code: &str) -> Self {
+        let mut app = TuringApp { code: code.to_string(), ..Default::default() };
+        assert!(app.compile(), "{}", app.diagnostic.as_ref().map_or("", |diagnostic| &diagnostic.message));
+        app
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::{BTreeSet, HashMap};
+
+use egui::{Pos2, Vec2, vec2};
+use itertools::Itertools;
+use rand::random_range;
+use turingrs::{parser::parse_turing_machine, turing_state::TuringTransition};
+
+use crate::{TuringApp, app::Selection};
+
+use super::{declaration::Declarations, machine};
+
+const POSITION: &str = "// position:";
+
+/// Copy and paste of sub-machines as text, so they can be pasted in another window.
+/// The text is written as code : the rules between the states copied,
+/// with their declarations and their position in comments.
+impl TuringApp {
+    /// The text of the states and of the transitions between them
+    pub fn copy_states(&self, ids: &BTreeSet<u8>) -> String {
+        let turing_machine = &self.turing.turing_machine;
+        let name = |id: u8| turing_machine.states[id as usize].name.to_string();
+
+        let accepting: Vec<String> = ids
+            .iter()
+            .filter(|id| turing_machine.states[**id as usize].is_final)
+            .map(|id| name(*id))
+            .collect();
+
+        let declarations = Declarations {
+            initial: None,
+            accepting: (!accepting.is_empty()).then_some(accepting),
+            states: ids.iter().map(|id| name(*id)).collect(),
+        };
+
+        let positions = ids
+            .iter()
+            .filter_map(|id| self.states.get(id))
+            .map(|state| format!("{} q_{} {} {}", POSITION, state.name, state.position.x, state.position.y));
+
+        let rules = ids.iter().flat_map(|id| {
+            turing_machine.states[*id as usize]
+                .transitions
+                .iter()
+                .filter(|transition| ids.contains(&transition.index_to_state))
+                .map(move |transition| {
+                    format!("q_{} {{{}}} q_{};", name(*id), transition, name(transition.index_to_state))
+                })
+        });
+
+        std::iter::once(declarations.to_code()).chain(positions).chain(rules).join("\n")
+    }
+
+    /// Add the states and transitions of the text to the machine, centered on the position.
+    /// The states taking a name already used are renamed, the states pasted are selected.
+    pub fn paste_states(&mut self, text: &str, center: Pos2) -> Result<(), String> {
+        let declarations = Declarations::parse(text);
+        let positions = parse_positions(text);
+        let rules = parse_rules(text)?;
+
+        let k = self.turing.turing_machine.k;
+        if rules.iter().any(|(_, transition, _)| transition.chars_read.len() != k as usize + 1) {
+            return Err(format!("The transitions pasted must read {} characters", k + 1));
+        }
+
+        // the states declared, then the states only found in the rules
+        let names: Vec<String> = declarations
+            .states
+            .iter()
+            .cloned()
+            .chain(rules.iter().flat_map(|(from, _, to)| [from.to_string(), to.to_string()]))
+            .unique()
+            .collect();
+
+        if names.is_empty() {
+            return Err("There is no state to paste".to_string());
+        }
+
+        // the states and rules are added to a copy of the machine, which is kept only if every rule can be added
+        let mut turing_machine = self.turing.turing_machine.clone();
+        let mut ids: HashMap<String, u8> = HashMap::new();
+        for name in names.iter() {
+            let unique_name = if turing_machine.name_index_hashmap.contains_key(name) {
+                machine::unique_name(&turing_machine, &format!("{}_", name))
+            } else {
+                name.to_string()
+            };
+
+            let id = machine::add_state(&mut turing_machine, &unique_name);
+            turing_machine.states[id as usize].is_final =
+                declarations.accepting.as_ref().is_some_and(|accepting| accepting.contains(name));
+            ids.insert(name.to_string(), id);
+        }
+
+        for (from, transition, to) in rules {
+            turing_machine
+                .append_rule_state(ids[&from], transition, ids[&to])
+                .map_err(|error| format!("{:?}", error))?;
+        }
+
+        self.turing.turing_machine = turing_machine;
+        self.machine_changed();
+
+        // move the states pasted around the position, keeping their layout
+        let known_positions: Vec<Pos2> = names.iter().filter_map(|name| positions.get(name)).copied().collect();
+        let offset = match known_positions.len() {
+            0 => Vec2::ZERO,
+            count => center - known_positions.iter().fold(Pos2::ZERO, |sum, position| sum + position.to_vec2()) / count as f32,
+        };
+
+        for name in names.iter() {
+            if let Some(state) = self.states.get_mut(&ids[name]) {
+                state.position = match positions.get(name) {
+                    Some(position) => *position + offset,
+                    None => center + vec2(random_range(-100.0..100.0), random_range(-100.0..100.0)),
+                };
+            }
+        }
+
+        self.selection = Selection {
+            selected_states: ids.into_values().collect(),
+            ..Default::default()
+        };
+
+        Ok(())
+    }
+}
+
+/// Read the positions written in the comments, by name of state
+fn parse_positions(text: &str) -> HashMap<String, Pos2> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix(POSITION))
+        .filter_map(|line| {
+            let (name, x, y) = line.split_whitespace().collect_tuple()?;
+            let name = name.strip_prefix("q_").unwrap_or(name).to_string();
+            Some((name, Pos2::new(x.parse().ok()?, y.parse().ok()?)))
+        })
+        .collect()
+}
+
+/// Parse the rules of the text, with the name of the state each rule comes from and the name of its target
+fn parse_rules(text: &str) -> Result<Vec<(String, TuringTransition, String)>, String> {
+    let code = text.lines().filter(|line| !line.trim_start().starts_with("//")).join("\n");
+    if code.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let turing_machine = parse_turing_machine(code).map_err(|error| error.to_string())?;
+
+    Ok(turing_machine
+        .states
+        .iter()
+        .flat_map(|state| {
+            state.transitions.iter().map(|transition| {
+                let target = &turing_machine.states[transition.index_to_state as usize].name;
+                (state.name.to_string(), transition.clone(), target.to_string())
+            })
+        })
+        .collect())
+}
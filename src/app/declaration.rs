@@ -0,0 +1,163 @@
+use turingrs::turing_machine::TuringMachine;
+
+use super::machine;
+
+const INITIAL: &str = "// initial:";
+const ACCEPTING: &str = "// accepting:";
+const STATES: &str = "// states:";
+
+/// The declarations written as comments at the top of the code.
+/// They keep what the rules alone can't express : the initial state,
+/// the accepting states and the states without any transition.
+#[derive(Default, PartialEq, Debug)]
+pub struct Declarations {
+    pub initial: Option<String>,
+    pub accepting: Option<Vec<String>>,
+    pub states: Vec<String>,
+}
+
+impl Declarations {
+    /// Read the declarations from the comments of the code, the names are without the `q_` prefix
+    pub fn parse(code: &str) -> Self {
+        let mut declarations = Declarations::default();
+
+        for line in code.lines().map(str::trim) {
+            if let Some(names) = line.strip_prefix(INITIAL) {
+                declarations.initial = state_names(names).into_iter().next();
+            } else if let Some(names) = line.strip_prefix(ACCEPTING) {
+                declarations.accepting = Some(state_names(names));
+            } else if let Some(names) = line.strip_prefix(STATES) {
+                declarations.states.extend(state_names(names));
+            }
+        }
+
+        declarations
+    }
+
+    /// Write the declarations as comments, one line per kind of declaration
+    pub fn to_code(&self) -> String {
+        let mut lines = vec![];
+
+        if let Some(initial) = &self.initial {
+            lines.push(format!("{} q_{}", INITIAL, initial));
+        }
+        if let Some(accepting) = &self.accepting {
+            lines.push(format!("{} {}", ACCEPTING, prefixed(accepting)));
+        }
+        if !self.states.is_empty() {
+            lines.push(format!("{} {}", STATES, prefixed(&self.states)));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Apply the declarations to a machine freshly parsed :
+    /// the missing states are added, the accepting states are marked and the initial state is moved first.
+    pub fn apply(&self, turing_machine: &mut TuringMachine) {
+        for name in self.states.iter() {
+            if !turing_machine.name_index_hashmap.contains_key(name) {
+                machine::add_state(turing_machine, name);
+            }
+        }
+
+        if let Some(accepting) = &self.accepting {
+            for state in turing_machine.states.iter_mut() {
+                state.is_final = accepting.contains(&state.name);
+            }
+        }
+
+        if let Some(index) = self.initial.as_ref().and_then(|initial| turing_machine.name_index_hashmap.get(initial)) {
+            machine::set_initial(turing_machine, *index);
+        }
+    }
+}
+
+/// Split the names of the states declared, removing the `q_` prefix
+fn state_names(names: &str) -> Vec<String> {
+    names
+        .split_whitespace()
+        .map(|name| name.strip_prefix("q_").unwrap_or(name).to_string())
+        .collect()
+}
+
+/// Join the names of the states with the `q_` prefix
+fn prefixed(names: &[String]) -> String {
+    names.iter().map(|name| format!("q_{}", name)).collect::<Vec<String>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use turingrs::parser::parse_turing_machine;
+
+    use super::Declarations;
+
+    const CODE: &str = "// initial: q_b
+// accepting: q_c q_d
+// states: q_e
+
+q_a {0, ç -> R, ç, N} q_b;
+q_b {1, ç -> L, ç, N} q_c;
+";
+
+    #[test]
+    fn declarations_are_parsed_without_prefix() {
+        let declarations = Declarations::parse(CODE);
+
+        assert_eq!(declarations, Declarations {
+            initial: Some("b".to_string()),
+            accepting: Some(vec!["c".to_string(), "d".to_string()]),
+            states: vec!["e".to_string()],
+        });
+    }
+
+    #[test]
+    fn written_declarations_are_parsed_back() {
+        let declarations = Declarations::parse(CODE);
+        let code = declarations.to_code();
+
+        assert_eq!(code, CODE.split("\n\n").next().unwrap());
+        assert_eq!(Declarations::parse(&code), declarations);
+    }
+
+    #[test]
+    fn code_without_declarations_has_none() {
+        let declarations = Declarations::parse("q_a {0, ç -> R, ç, N} q_b;");
+
+        assert_eq!(declarations, Declarations::default());
+        assert_eq!(declarations.to_code(), "");
+    }
+
+    #[test]
+    fn unknown_names_are_added_or_ignored() {
+        let mut turing_machine = parse_turing_machine("q_a {0, ç -> R, ç, N} q_b;".to_string()).unwrap();
+        let declarations = Declarations {
+            initial: Some("x".to_string()),
+            accepting: Some(vec!["b".to_string(), "y".to_string()]),
+            states: vec!["z".to_string()],
+        };
+        declarations.apply(&mut turing_machine);
+
+        // the unknown initial state is ignored, the declared state is added
+        assert_eq!(turing_machine.states[0].name, "a");
+        assert!(turing_machine.name_index_hashmap.contains_key("z"));
+        assert!(!turing_machine.name_index_hashmap.contains_key("x"));
+        assert!(!turing_machine.name_index_hashmap.contains_key("y"));
+
+        let accepting: Vec<&str> = turing_machine.states
+            .iter()
+            .filter(|state| state.is_final)
+            .map(|state| state.name.as_str())
+            .collect();
+        assert_eq!(accepting, vec!["b"]);
+    }
+
+    #[test]
+    fn initial_state_is_moved_first() {
+        let mut turing_machine = parse_turing_machine("q_a {0, ç -> R, ç, N} q_b;".to_string()).unwrap();
+        Declarations::parse(CODE).apply(&mut turing_machine);
+
+        assert_eq!(turing_machine.states[0].name, "b");
+        assert_eq!(turing_machine.name_index_hashmap["b"], 0);
+        assert_eq!(turing_machine.states[1].transitions[0].index_to_state, 0);
+    }
+}
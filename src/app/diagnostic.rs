@@ -0,0 +1,99 @@
+/// An error raised while compiling the code or creating the executor.
+/// The line and column are 1-based, as displayed in the gutter of the code panel.
+pub struct Diagnostic {
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    /// Create a diagnostic from the message of an error.
+    /// The position is read from the ` --> line:column` header of the parser errors if there is one.
+    pub fn new(message: String) -> Self {
+        let position = message
+            .lines()
+            .find_map(|line| line.trim_start().strip_prefix("--> "))
+            .and_then(|position| position.trim().split_once(':'))
+            .and_then(|(line, column)| Some((line.parse().ok()?, column.parse().ok()?)));
+
+        Self {
+            message,
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+        }
+    }
+
+    /// Create a diagnostic which doesn't point to the code,
+    /// for the errors of a text which isn't the code of the editor.
+    pub fn without_position(message: String) -> Self {
+        Self { message, line: None, column: None }
+    }
+
+    /// The byte range of the code underlined by the diagnostic,
+    /// from the column to the end of the word it points to.
+    pub fn span(&self, code: &str) -> Option<std::ops::Range<usize>> {
+        let (line, column) = (self.line?, self.column?);
+
+        // byte offset of the start of the line
+        let line_start: usize = code
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(|l| l.len())
+            .sum();
+        let line_text = code[line_start..].lines().next().unwrap_or("");
+
+        let start = line_text
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map(|(i, _)| i)
+            .unwrap_or(line_text.len());
+        let end = line_text[start..]
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, _)| start + i)
+            .unwrap_or(line_text.len());
+
+        Some(line_start + start..line_start + end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Diagnostic;
+
+    const CODE: &str = "q_a {0, ç -> R, ç, N} q_b;\nq_b {1, ç -> X, ç, N} q_a;\n";
+
+    #[test]
+    fn position_is_read_from_the_header() {
+        let diagnostic = Diagnostic::new(" --> 2:14\n  |\n2 | q_b {1, ç -> X, ç, N} q_a;\n  |".to_string());
+
+        assert_eq!((diagnostic.line, diagnostic.column), (Some(2), Some(14)));
+    }
+
+    #[test]
+    fn message_without_header_has_no_position() {
+        let diagnostic = Diagnostic::new("Invalid transition".to_string());
+
+        assert_eq!((diagnostic.line, diagnostic.column), (None, None));
+        assert_eq!(diagnostic.span(CODE), None);
+        assert_eq!(Diagnostic::without_position(" --> 2:14".to_string()).line, None);
+    }
+
+    #[test]
+    fn span_covers_the_word_pointed() {
+        let diagnostic = Diagnostic::new(" --> 2:14".to_string());
+        let span = diagnostic.span(CODE).unwrap();
+
+        assert_eq!(&CODE[span], "X,");
+    }
+
+    #[test]
+    fn span_out_of_the_code_is_empty() {
+        let after_end = Diagnostic::new(" --> 10:3".to_string()).span(CODE).unwrap();
+        assert_eq!(after_end, CODE.len()..CODE.len());
+
+        let line_zero = Diagnostic::new(" --> 0:1".to_string()).span(CODE).unwrap();
+        assert_eq!(&CODE[line_zero], "q_a");
+    }
+}
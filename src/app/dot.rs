@@ -0,0 +1,341 @@
+use std::{collections::HashMap, fmt::Write, iter::Peekable, str::Chars};
+
+use egui::Pos2;
+use itertools::Itertools;
+
+use crate::{TuringApp, ui::constant::Constant};
+
+use super::machine;
+
+/// The invisible node the arrow to the initial state comes from
+const START_NODE: &str = "__start";
+
+/// The words of the DOT language that are not nodes
+const KEYWORDS: [&str; 6] = ["strict", "graph", "digraph", "subgraph", "node", "edge"];
+
+/// A token of the DOT language, the keywords are identifiers
+#[derive(PartialEq, Debug)]
+enum Token {
+    Id(String),
+    /// `->` or `--`
+    EdgeOp,
+    Punct(char),
+}
+
+/// Export of the graph to Graphviz, and import of the positions of a layout computed by Graphviz.
+/// The positions are in points, one point per pixel of the graph, with the y axis going up.
+impl TuringApp {
+    /// The graph as a DOT digraph, the states are nodes with the position they have in the graph
+    pub fn to_dot(&self) -> String {
+        let turing_machine = &self.turing.turing_machine;
+        let mut dot = String::new();
+
+        let _ = writeln!(dot, "digraph {{");
+        // the nodes have the size of the states to be laid out with the same spacing
+        let _ = writeln!(
+            dot,
+            "    node [shape=circle, fixedsize=true, width={:.2}];",
+            Constant::STATE_RADIUS * 2.0 / Constant::DOT_POINTS_PER_INCH,
+        );
+        let _ = writeln!(dot, "    \"{}\" [shape=point, style=invis];", START_NODE);
+
+        for state in self.states.values().sorted_by_key(|state| state.id) {
+            let shape = if turing_machine.states[state.id as usize].is_final { "shape=doublecircle, " } else { "" };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [{}pos=\"{:.2},{:.2}\"];",
+                escape(&state.name),
+                shape,
+                state.position.x,
+                -state.position.y,
+            );
+        }
+
+        if let Some(initial) = turing_machine.states.first() {
+            let _ = writeln!(dot, "    \"{}\" -> \"{}\";", START_NODE, escape(&initial.name));
+        }
+
+        for (from, to, transitions) in machine::edges(turing_machine) {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                escape(&turing_machine.states[from as usize].name),
+                escape(&turing_machine.states[to as usize].name),
+                transitions.iter().map(|transition| escape(&transition.to_string())).join("\\n"),
+            );
+        }
+
+        let _ = writeln!(dot, "}}");
+        dot
+    }
+
+    /// Move the states to the position of the nodes with the same name in the DOT text.
+    /// The states are animated to their position as for an automatic layout.
+    pub fn import_dot_positions(&mut self, text: &str) -> Result<(), String> {
+        let positions = parse_positions(text)?;
+
+        let targets: HashMap<u8, Pos2> = self.states
+            .values()
+            .filter_map(|state| positions.get(&state.name).map(|position| (state.id, *position)))
+            .collect();
+
+        if targets.is_empty() {
+            return Err("No node with a position has the name of a state".to_string());
+        }
+
+        self.animate_to(targets);
+        Ok(())
+    }
+}
+
+/// Escape a text written between double quotes
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The positions of the nodes having a `pos` attribute, by name of node.
+/// The attributes of the edges and the default attributes are ignored.
+fn parse_positions(text: &str) -> Result<HashMap<String, Pos2>, String> {
+    let tokens = tokenize(text)?;
+    let mut positions: HashMap<String, Pos2> = HashMap::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let Token::Id(id) = &tokens[i] else {
+            i += 1;
+            continue;
+        };
+
+        // an attribute of the graph, `id = value`
+        if tokens.get(i + 1) == Some(&Token::Punct('=')) {
+            i += 3;
+            continue;
+        }
+
+        let is_edge = tokens.get(i + 1) == Some(&Token::EdgeOp) || (i > 0 && tokens[i - 1] == Token::EdgeOp);
+        let is_node = !is_edge && !KEYWORDS.contains(&id.to_lowercase().as_str());
+        i += 1;
+
+        // the port of the node, `id:port`
+        while tokens.get(i) == Some(&Token::Punct(':')) {
+            i += 2;
+        }
+
+        // the attribute lists of the statement, `[key=value, ...][...]`
+        while tokens.get(i) == Some(&Token::Punct('[')) {
+            i += 1;
+            while let Some(token) = tokens.get(i) {
+                match (token, tokens.get(i + 1), tokens.get(i + 2)) {
+                    (Token::Punct(']'), ..) => {
+                        i += 1;
+                        break;
+                    }
+                    (Token::Id(key), Some(Token::Punct('=')), Some(Token::Id(value))) => {
+                        if is_node && key == "pos" {
+                            if let Some(position) = parse_pos(value) {
+                                positions.insert(id.to_string(), position);
+                            }
+                        }
+                        i += 3;
+                    }
+                    _ => i += 1,
+                }
+            }
+        }
+    }
+
+    Ok(positions)
+}
+
+/// Read a position `x,y` in points, with a `!` if the node is pinned.
+/// The y axis is flipped as it goes up in Graphviz.
+fn parse_pos(value: &str) -> Option<Pos2> {
+    let mut coordinates = value.trim().trim_end_matches('!').split(',');
+    let x: f32 = coordinates.next()?.trim().parse().ok()?;
+    let y: f32 = coordinates.next()?.trim().parse().ok()?;
+
+    Some(Pos2::new(x, -y))
+}
+
+/// Split the text in tokens, the comments are skipped
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            // the lines of the preprocessor are skipped as comments
+            '#' => skip_line(&mut chars),
+            '/' if chars.peek() == Some(&'/') => skip_line(&mut chars),
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '"' => tokens.push(Token::Id(quoted(&mut chars)?)),
+            '<' => tokens.push(Token::Id(html(&mut chars)?)),
+            '-' if matches!(chars.peek(), Some('>' | '-')) => {
+                chars.next();
+                tokens.push(Token::EdgeOp);
+            }
+            '{' | '}' | '[' | ']' | '=' | ';' | ',' | ':' => tokens.push(Token::Punct(c)),
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut id = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '.') {
+                    id.push(c);
+                }
+                tokens.push(Token::Id(id));
+            }
+            c => return Err(format!("Unexpected character '{}'", c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn skip_line(chars: &mut Peekable<Chars>) {
+    for c in chars.by_ref() {
+        if c == '\n' {
+            break;
+        }
+    }
+}
+
+/// Read a string until its closing quote, the escaped quotes and the escaped line breaks are unescaped
+fn quoted(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut text = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Ok(text),
+            '\\' => match chars.next() {
+                Some('"') => text.push('"'),
+                // a long string is split on several lines
+                Some('\n') => {}
+                Some('\r') => {
+                    chars.next_if_eq(&'\n');
+                }
+                Some(c) => {
+                    text.push('\\');
+                    text.push(c);
+                }
+                None => break,
+            },
+            c => text.push(c),
+        }
+    }
+
+    Err("A string is not closed".to_string())
+}
+
+/// Read a HTML string until its closing bracket, the brackets inside it are kept
+fn html(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut text = String::new();
+    let mut depth = 1;
+
+    for c in chars.by_ref() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(text);
+                }
+            }
+            _ => {}
+        }
+        text.push(c);
+    }
+
+    Err("A HTML string is not closed".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Pos2;
+
+    use crate::TuringApp;
+
+    use super::parse_positions;
+
+    #[test]
+    fn positions_are_flipped_vertically() {
+        let positions = parse_positions("digraph { a [pos=\"10,20\"]; b [pos=\"-5.5,0\"]; }").unwrap();
+
+        assert_eq!(positions["a"], Pos2::new(10.0, -20.0));
+        assert_eq!(positions["b"], Pos2::new(-5.5, 0.0));
+    }
+
+    #[test]
+    fn pinned_positions_are_read() {
+        let positions = parse_positions("digraph { a [pos=\"10,20!\"]; }").unwrap();
+
+        assert_eq!(positions["a"], Pos2::new(10.0, -20.0));
+    }
+
+    #[test]
+    fn edge_positions_are_ignored() {
+        let text = "digraph {
+            node [pos=\"1,1\"];
+            a -> b [pos=\"e,1,2 3,4 5,6 7,8\"];
+            b [pos=\"30,40\"];
+        }";
+        let positions = parse_positions(text).unwrap();
+
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions["b"], Pos2::new(30.0, -40.0));
+    }
+
+    #[test]
+    fn ports_are_skipped() {
+        let positions = parse_positions("digraph { a:n -> b:s:e; a:n [pos=\"1,2\"]; }").unwrap();
+
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions["a"], Pos2::new(1.0, -2.0));
+    }
+
+    #[test]
+    fn quoted_ids_are_unescaped() {
+        let text = "digraph {
+            \"a \\\"b\\\"\" [pos=\"1,2\"];
+            \"long \\
+name\" [pos=\"3,4\"];
+            // \"c\" [pos=\"5,6\"];
+            /* \"d\" [pos=\"7,8\"]; */
+            <e> [pos=\"9,10\"];
+        }";
+        let positions = parse_positions(text).unwrap();
+
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions["a \"b\""], Pos2::new(1.0, -2.0));
+        assert_eq!(positions["long name"], Pos2::new(3.0, -4.0));
+        assert_eq!(positions["e"], Pos2::new(9.0, -10.0));
+    }
+
+    #[test]
+    fn unclosed_string_is_an_error() {
+        assert!(parse_positions("digraph { \"a }").is_err());
+        assert!(parse_positions("digraph { <a <b> }").is_err());
+    }
+
+    #[test]
+    fn exported_positions_are_imported() {
+        let mut app = TuringApp::compiled("q_a {0, ç -> R, ç, N} q_b;\nq_b {1, ç -> L, ç, N} q_a;");
+        for (state, x) in app.states.values_mut().zip([12.5, -40.0]) {
+            state.position = Pos2::new(x, 30.0);
+        }
+
+        let positions = parse_positions(&app.to_dot()).unwrap();
+
+        assert_eq!(positions.len(), app.states.len());
+        for state in app.states.values() {
+            assert_eq!(positions[&state.name], state.position);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use egui::Pos2;
+use itertools::Itertools;
+use turingrs::turing_state::TuringTransition;
+
+use crate::{TuringApp, ui::turing::State};
+
+use super::{Selection, machine};
+
+/// The edits of the graph modifying the turing machine.
+/// The graph is rebuilt from the machine afterward and the execution restart,
+/// so no index of the previous graph is kept in the selection or the history.
+impl TuringApp {
+    /// Delete states and all the transitions from or to them, the initial state can't be deleted
+    pub fn delete_states(&mut self, ids: &[u8]) {
+        let state_count = self.turing.turing_machine.states.len();
+        let ids: Vec<u8> = ids
+            .iter()
+            .copied()
+            .filter(|id| !machine::is_initial(*id) && (*id as usize) < state_count)
+            .sorted()
+            .dedup()
+            .collect();
+
+        if ids.is_empty() {
+            return;
+        }
+
+        // the states are removed from the last one, so the index of the next ones don't shift
+        for id in ids.into_iter().rev() {
+            machine::remove_state(&mut self.turing.turing_machine, id);
+        }
+        self.machine_changed();
+    }
+
+    /// Delete a transition of a state
+    pub fn delete_transition(&mut self, parent_id: u8, id: u8) {
+        if self.turing.turing_machine.states
+            .get(parent_id as usize)
+            .is_none_or(|state| id as usize >= state.transitions.len())
+        {
+            return;
+        }
+
+        machine::remove_transition(&mut self.turing.turing_machine, parent_id, id);
+        self.machine_changed();
+    }
+
+    /// Replace a transition of a state in the machine and in the graph, the transition keeps its target.
+    /// The transition is first added to a copy of the machine without the old one, so it is checked by the machine.
+    pub fn replace_transition(&mut self, parent_id: u8, id: u8, mut transition: TuringTransition) -> Result<(), String> {
+        let Some(target) = self.turing.turing_machine.states
+            .get(parent_id as usize)
+            .and_then(|state| state.transitions.get(id as usize))
+            .map(|transition| transition.index_to_state)
+        else {
+            return Ok(());
+        };
+
+        let mut turing_machine = self.turing.turing_machine.clone();
+        machine::remove_transition(&mut turing_machine, parent_id, id);
+        turing_machine
+            .append_rule_state(parent_id, transition.clone(), target)
+            .map_err(|error| format!("{:?}", error))?;
+
+        transition.index_to_state = target;
+
+        let graph_transition = self.states
+            .get_mut(&parent_id)
+            .and_then(|state| state.transitions.iter_mut().find(|transition| transition.id == id));
+        if let Some(graph_transition) = graph_transition {
+            graph_transition.text = transition.to_string();
+        }
+
+        // the transition is replaced in place, so it keeps its order in the state
+        self.turing.turing_machine.states[parent_id as usize].transitions[id as usize] = transition;
+
+        Ok(())
+    }
+
+    /// Make a state accepting, or not accepting if it already is, the execution restart
+    pub fn toggle_accepting(&mut self, id: u8) {
+        let Some(state) = self.turing.turing_machine.states.get_mut(id as usize) else {
+            return;
+        };
+
+        state.is_final = !state.is_final;
+        self.machine_changed();
+    }
+
+    /// Make a state the initial one, the execution start from it
+    pub fn set_initial_state(&mut self, id: u8) {
+        if machine::is_initial(id) || id as usize >= self.turing.turing_machine.states.len() {
+            return;
+        }
+
+        machine::set_initial(&mut self.turing.turing_machine, id);
+        self.machine_changed();
+    }
+
+    /// Add a new state at the position with the first number not used as a name.
+    /// The state is selected and ready to be renamed.
+    pub fn add_state_at(&mut self, position: Pos2) {
+        let name = self.unique_state_name("");
+        let id = machine::add_state(&mut self.turing.turing_machine, &name);
+
+        self.selection = Selection::rename_state(id, &name);
+        self.states.insert(id, State::new_at_pos(id, name, position));
+    }
+
+    /// Rename a state in the machine and in the graph, the references to it in the code are rewritten.
+    /// The name must be a valid identifier which is not used by another state.
+    pub fn rename_state(&mut self, id: u8, name: &str) -> Result<(), String> {
+        let name = name.trim();
+        let Some(old_name) = self.turing.turing_machine.states.get(id as usize).map(|state| state.name.to_string()) else {
+            return Ok(());
+        };
+
+        if name == old_name {
+            return Ok(());
+        }
+
+        machine::validate_name(name)?;
+        if self.turing.turing_machine.name_index_hashmap.contains_key(name) {
+            return Err(format!("The state q_{} already exists", name));
+        }
+
+        machine::rename_state(&mut self.turing.turing_machine, id, name);
+        if let Some(state) = self.states.get_mut(&id) {
+            state.name = name.to_string();
+        }
+
+        // the curves to the state keep their offset
+        for state in self.states.values_mut() {
+            if let Some(offset) = state.curves.remove(&old_name) {
+                state.curves.insert(name.to_string(), offset);
+            }
+        }
+
+        self.code = rename_references(&self.code, &old_name, name);
+        self.is_dirty = true;
+        self.record_edit();
+
+        Ok(())
+    }
+
+    /// The first name made of the prefix followed by a number which is not used by a state
+    pub fn unique_state_name(&self, prefix: &str) -> String {
+        machine::unique_name(&self.turing.turing_machine, prefix)
+    }
+
+    /// Rebuild the graph after an edit of the machine and restart the execution
+    pub fn machine_changed(&mut self) {
+        self.selection = Selection::default();
+        self.code_to_graph();
+        self.update();
+    }
+}
+
+/// Replace the references to a state in the code, the names only starting like it are kept
+fn rename_references(code: &str, old_name: &str, new_name: &str) -> String {
+    let old_reference = format!("q_{}", old_name);
+    let new_reference = format!("q_{}", new_name);
+    let is_identifier = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut renamed = String::with_capacity(code.len());
+    let mut rest = code;
+
+    while let Some(index) = rest.find(&old_reference) {
+        renamed.push_str(&rest[..index]);
+        rest = &rest[index + old_reference.len()..];
+
+        let is_reference = !renamed.ends_with(is_identifier) && !rest.starts_with(is_identifier);
+        renamed.push_str(if is_reference { &new_reference } else { &old_reference });
+    }
+    renamed.push_str(rest);
+
+    renamed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rename_references;
+
+    #[test]
+    fn references_are_renamed() {
+        let code = "// initial: q_a\nq_a {0, ç -> R, ç, N} q_b;\nq_b {1, ç -> L, ç, N} q_a;";
+
+        assert_eq!(
+            rename_references(code, "a", "start"),
+            "// initial: q_start\nq_start {0, ç -> R, ç, N} q_b;\nq_b {1, ç -> L, ç, N} q_start;"
+        );
+    }
+
+    #[test]
+    fn longer_names_are_kept() {
+        let code = "q_a {0, ç -> R, ç, N} q_ab;\nq_ab {0, ç -> R, ç, N} q_a_1;";
+
+        assert_eq!(rename_references(code, "a", "c"), "q_c {0, ç -> R, ç, N} q_ab;\nq_ab {0, ç -> R, ç, N} q_a_1;");
+    }
+
+    #[test]
+    fn names_ending_like_the_reference_are_kept() {
+        assert_eq!(rename_references("xq_a q_a", "a", "b"), "xq_a q_b");
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use crate::TuringApp;
+
+use super::declaration::Declarations;
+
+/// A part of the code, either a rule or anything else written between the rules
+#[derive(Debug, PartialEq)]
+enum Piece {
+    Rule(Rule),
+    /// The comments, the whitespaces and the text which is not a rule
+    Text(String),
+}
+
+/// A rule of the code `q_from {transition | ...} q_to;` as written by the user
+#[derive(Debug, PartialEq)]
+struct Rule {
+    text: String,
+    from: String,
+    to: String,
+    transitions: Vec<String>,
+}
+
+/// Generation of the code from the graph
+impl TuringApp {
+    /// Generate the code from the graph, keeping the code already written as much as possible.
+    /// The rules whose transitions didn't change are kept as they are written, with the comments and the lines between them.
+    /// The rules that changed are regrouped by source and target where they were,
+    /// the new rules are placed after the other rules of their source state or at the end.
+    /// Without code the output is deterministic : the rules are grouped by source and target in the order of their index,
+    /// and the declarations keep the initial state, the accepting states and the states without transitions.
+    pub fn graph_to_code(&mut self) {
+        let mut pieces = split_code(&self.code);
+
+        set_declarations(&mut pieces, &self.code, &self.declarations());
+
+        // the rules of the graph grouped by source then target, in the order of the index
+        let rules = self.states.values()
+            .sorted_by_key(|state| state.id)
+            .flat_map(|state| {
+                state.transitions.iter()
+                    .sorted_by_key(|transition| (transition.target_id, transition.id))
+                    .chunk_by(|transition| transition.target_id)
+                    .into_iter()
+                    .map(|(target_id, transitions)| Rule::new(
+                        &state.name,
+                        &self.states[&target_id].name,
+                        transitions.map(|transition| transition.text.to_string()).collect(),
+                    ))
+                    .collect::<Vec<Rule>>()
+            })
+            .collect::<Vec<Rule>>();
+
+        set_rules(&mut pieces, rules);
+
+        self.code = pieces.iter()
+            .map(|piece| match piece {
+                Piece::Rule(rule) => &rule.text,
+                Piece::Text(text) => text,
+            })
+            .join("");
+        self.is_dirty = true;
+    }
+
+    /// The declarations of the initial state, the accepting states and the states without any transition
+    fn declarations(&self) -> Declarations {
+        let turing_machine = &self.turing.turing_machine;
+
+        // states without any transition from or to them
+        let isolated_states = self.states.values()
+            .filter(|state| state.transitions.is_empty())
+            .filter(|state| !self.states.values().flat_map(|s| s.transitions.iter()).any(|t| t.target_id == state.id))
+            .sorted_by_key(|state| state.id)
+            .map(|state| state.name.to_string())
+            .collect();
+
+        Declarations {
+            initial: self.states.get(&0).map(|state| state.name.to_string()),
+            accepting: Some(
+                self.states.values()
+                    .filter(|state| turing_machine.states.get(state.id as usize).is_some_and(|s| s.is_final))
+                    .sorted_by_key(|state| state.id)
+                    .map(|state| state.name.to_string())
+                    .collect()
+            ),
+            states: isolated_states,
+        }
+    }
+}
+
+impl Rule {
+    /// A rule written in the default format, one transition per line
+    fn new(from: &str, to: &str, transitions: Vec<String>) -> Self {
+        Self {
+            text: format!("q_{} {{{}}} q_{};", from, transitions.join("\n | "), to),
+            from: from.to_string(),
+            to: to.to_string(),
+            transitions,
+        }
+    }
+
+    /// Read a rule from its text, ending with the semicolon.
+    /// The comments inside the brackets are ignored.
+    fn parse(text: &str) -> Option<Self> {
+        let (from, rest) = text.split_once('{')?;
+        let (body, to) = rest.rsplit_once('}')?;
+        let from = from.trim().strip_prefix("q_")?;
+        let to = to.trim().strip_suffix(';')?.trim().strip_prefix("q_")?;
+
+        let body = body.lines().map(|line| line.split("//").next().unwrap_or_default()).join("\n");
+        let transitions = body.split('|').map(|transition| transition.trim().to_string()).collect();
+
+        Some(Self {
+            text: text.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            transitions,
+        })
+    }
+
+    /// True if the rules have the same transitions, whatever the spaces between their characters
+    fn has_same_transitions(&self, transitions: &[String]) -> bool {
+        let compact = |text: &String| text.split_whitespace().collect::<String>();
+        self.transitions.iter().map(compact).eq(transitions.iter().map(compact))
+    }
+}
+
+/// Split the code into rules and texts between them, the whitespaces are texts on their own.
+/// Every character of the code belongs to a piece.
+fn split_code(code: &str) -> Vec<Piece> {
+    let mut pieces = vec![];
+    let mut rest = code;
+
+    while let Some(c) = rest.chars().next() {
+        let length = if c.is_whitespace() {
+            rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len())
+        } else if rest.starts_with("q_") {
+            match rule_length(rest).and_then(|length| Rule::parse(&rest[..length]).map(|rule| (length, rule))) {
+                Some((length, rule)) => {
+                    pieces.push(Piece::Rule(rule));
+                    rest = &rest[length..];
+                    continue;
+                }
+                None => line_length(rest),
+            }
+        } else {
+            line_length(rest)
+        };
+
+        pieces.push(Piece::Text(rest[..length].to_string()));
+        rest = &rest[length..];
+    }
+
+    pieces
+}
+
+/// The length of the rule at the start of the code until its semicolon, the comments are skipped
+fn rule_length(code: &str) -> Option<usize> {
+    let mut in_brackets = false;
+    let mut chars = code.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '/' if chars.peek().is_some_and(|(_, c)| *c == '/') => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+            }
+            '{' => in_brackets = true,
+            '}' => in_brackets = false,
+            ';' if !in_brackets => return Some(index + 1),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// The length of the line at the start of the code, without the line break
+fn line_length(code: &str) -> usize {
+    code.find('\n').unwrap_or(code.len())
+}
+
+/// Replace the declarations written in the code if they changed.
+/// The new declarations take the place of the first one, they are written at the top if there was none.
+fn set_declarations(pieces: &mut Vec<Piece>, code: &str, declarations: &Declarations) {
+    if Declarations::parse(code) == *declarations {
+        return;
+    }
+
+    let is_declaration = |piece: &Piece| {
+        matches!(piece, Piece::Text(text) if Declarations::parse(text) != Declarations::default())
+    };
+
+    match pieces.iter().position(is_declaration) {
+        Some(index) => {
+            pieces[index] = Piece::Text(declarations.to_code());
+            while let Some(index) = pieces.iter().skip(index + 1).position(is_declaration).map(|i| i + index + 1) {
+                remove_piece(pieces, index);
+            }
+        }
+        None if pieces.is_empty() => {
+            pieces.push(Piece::Text(declarations.to_code()));
+            pieces.push(Piece::Text("\n".to_string()));
+        }
+        None => {
+            pieces.insert(0, Piece::Text(declarations.to_code()));
+            pieces.insert(1, Piece::Text("\n\n".to_string()));
+        }
+    }
+}
+
+/// Replace the rules of the code by the rules of the graph.
+/// The rules with the same transitions are kept, the others are rewritten in place of the first rule
+/// with the same source and target, and the rules without any transition left are removed.
+fn set_rules(pieces: &mut Vec<Piece>, rules: Vec<Rule>) {
+    let mut written: HashMap<(String, String), Vec<String>> = HashMap::new();
+    for piece in pieces.iter() {
+        if let Piece::Rule(rule) = piece {
+            written.entry((rule.from.to_string(), rule.to.to_string()))
+                .or_default()
+                .extend(rule.transitions.iter().cloned());
+        }
+    }
+
+    for rule in rules {
+        let key = (rule.from.to_string(), rule.to.to_string());
+        let same_edge = |piece: &Piece| matches!(piece, Piece::Rule(r) if r.from == key.0 && r.to == key.1);
+
+        let is_unchanged = written
+            .remove(&key)
+            .is_some_and(|transitions| rule.has_same_transitions(&transitions));
+        if is_unchanged {
+            continue;
+        }
+
+        match pieces.iter().position(same_edge) {
+            // the rules of the edge are merged in place of the first one
+            Some(index) => {
+                pieces[index] = Piece::Rule(rule);
+                while let Some(other) = pieces.iter().skip(index + 1).position(same_edge).map(|i| i + index + 1) {
+                    remove_piece(pieces, other);
+                }
+            }
+            // a new edge is written after the last rule of its source state, or after the last rule
+            None => {
+                let index = pieces.iter()
+                    .rposition(|piece| matches!(piece, Piece::Rule(r) if r.from == rule.from))
+                    .or_else(|| pieces.iter().rposition(|piece| matches!(piece, Piece::Rule(_))))
+                    .map(|index| index + 1)
+                    .unwrap_or_else(|| pieces.iter().rposition(|piece| !is_blank(piece)).map_or(0, |index| index + 1));
+
+                pieces.insert(index, Piece::Text("\n\n".to_string()));
+                pieces.insert(index + 1, Piece::Rule(rule));
+                if index + 2 == pieces.len() {
+                    pieces.push(Piece::Text("\n".to_string()));
+                }
+            }
+        }
+    }
+
+    // the edges without transitions anymore
+    while let Some(index) = pieces.iter().position(|piece| {
+        matches!(piece, Piece::Rule(rule) if written.contains_key(&(rule.from.to_string(), rule.to.to_string())))
+    }) {
+        remove_piece(pieces, index);
+    }
+}
+
+/// True if the piece is only whitespaces
+fn is_blank(piece: &Piece) -> bool {
+    matches!(piece, Piece::Text(text) if text.trim().is_empty())
+}
+
+/// Remove a piece and the whitespaces before it, or after it if it's the first piece
+fn remove_piece(pieces: &mut Vec<Piece>, index: usize) {
+    pieces.remove(index);
+
+    if index > 0 && is_blank(&pieces[index - 1]) {
+        pieces.remove(index - 1);
+    } else if index < pieces.len() && is_blank(&pieces[index]) {
+        pieces.remove(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use turingrs::{parser::parse_turing_machine, turing_machine::TuringMachine};
+
+    use crate::{TuringApp, app::declaration::Declarations};
+
+    /// A machine with an initial state which is not the first in the rules,
+    /// two accepting states and a state without transitions
+    const CODE: &str = "// initial: q_b
+// accepting: q_c q_d
+// states: q_e
+
+q_a {0, ç -> R, 0, R} q_b;
+
+// go back
+q_b {1, ç -> L, ç, N
+ | 0, ç -> L, 1, N} q_a;
+
+q_b {ç, ç -> N, ç, N} q_c;
+
+q_a {ç, ç -> N, ç, N} q_d;
+";
+
+    fn parse(code: &str) -> TuringMachine {
+        let mut turing_machine = parse_turing_machine(code.to_string()).expect("the code can be parsed");
+        Declarations::parse(code).apply(&mut turing_machine);
+        turing_machine
+    }
+
+    type Summary = Vec<(String, bool, Vec<(String, String)>)>;
+
+    /// The states by name with their final flag and their transitions to the states by name
+    fn summary(turing_machine: &TuringMachine) -> Summary {
+        let mut states: Summary = turing_machine.states
+            .iter()
+            .map(|state| {
+                let mut transitions: Vec<(String, String)> = state.transitions
+                    .iter()
+                    .map(|transition| {
+                        (transition.to_string(), turing_machine.states[transition.index_to_state as usize].name.to_string())
+                    })
+                    .collect();
+                transitions.sort();
+                (state.name.to_string(), state.is_final, transitions)
+            })
+            .collect();
+        states.sort();
+        states
+    }
+
+    #[test]
+    fn generated_code_gives_the_same_machine() {
+        let mut app = TuringApp::compiled(CODE);
+        app.code = String::new();
+        app.graph_to_code();
+
+        let turing_machine = parse(&app.code);
+        assert_eq!(summary(&turing_machine), summary(&app.turing.turing_machine));
+        assert_eq!(turing_machine.states[0].name, "b");
+        assert!(turing_machine.name_index_hashmap.contains_key("e"));
+        assert!(turing_machine.states.iter().filter(|state| state.is_final).map(|state| &state.name).eq(["c", "d"].iter()));
+    }
+
+    #[test]
+    fn generated_code_is_stable() {
+        let mut app = TuringApp::compiled(CODE);
+        app.code = String::new();
+        app.graph_to_code();
+        let first = app.code.to_string();
+
+        let mut app = TuringApp::compiled(&first);
+        app.code = String::new();
+        app.graph_to_code();
+        assert_eq!(app.code, first);
+
+        let mut app = TuringApp::compiled(&first);
+        app.graph_to_code();
+        assert_eq!(app.code, first);
+    }
+
+    #[test]
+    fn unchanged_code_is_kept() {
+        let mut app = TuringApp::compiled(CODE);
+        app.graph_to_code();
+        assert_eq!(app.code, CODE);
+    }
+
+    #[test]
+    fn only_the_changed_rule_is_rewritten() {
+        let mut app = TuringApp::compiled(CODE);
+        let a = app.turing.turing_machine.name_index_hashmap["a"];
+        let b = app.turing.turing_machine.name_index_hashmap["b"];
+        let transition = app.states[&b].transitions.iter().position(|t| t.target_id == a && t.text.starts_with('1')).unwrap();
+        app.states.get_mut(&b).unwrap().transitions.remove(transition);
+        app.graph_to_code();
+
+        assert_eq!(app.code, CODE.replace("q_b {1, ç -> L, ç, N\n | 0, ç -> L, 1, N} q_a;", "q_b {0, ç -> L, 1, N} q_a;"));
+    }
+
+    #[test]
+    fn removed_rule_and_declaration_are_updated() {
+        let mut app = TuringApp::compiled(CODE);
+        let a = app.turing.turing_machine.name_index_hashmap["a"];
+        let d = app.turing.turing_machine.name_index_hashmap["d"];
+        app.states.get_mut(&a).unwrap().transitions.retain(|transition| transition.target_id != d);
+        app.graph_to_code();
+
+        let expected = CODE
+            .replace("// states: q_e", "// states: q_d q_e")
+            .replace("\n\nq_a {ç, ç -> N, ç, N} q_d;", "");
+        assert_eq!(app.code, expected);
+    }
+}
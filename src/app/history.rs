@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+
+use turingrs::turing_machine::{TuringExecutionStep, TuringMachine};
+
+use crate::ui::constant::Constant;
+
+/// A step computed by the executor, the state the machine was in after it
+/// and the transition (source, transition index) that produced it
+pub struct HistoryStep {
+    pub step: TuringExecutionStep,
+    pub state: u8,
+    pub transition: Option<(u8, u8)>,
+}
+
+/// The last steps computed since the start of the execution, at most `Constant::HISTORY_LIMIT`.
+/// The oldest steps are dropped but the indexes keep counting from the initial step.
+/// The cursor point to the step currently displayed, it can go back in time
+/// without altering the executor which always stay on the last step.
+pub struct History {
+    steps: VecDeque<HistoryStep>,
+    /// The index of the oldest step kept
+    first: usize,
+    cursor: usize,
+}
+
+impl History {
+    pub fn new(initial_step: TuringExecutionStep, state: u8) -> Self {
+        Self {
+            steps: VecDeque::from([HistoryStep {
+                step: initial_step,
+                state,
+                transition: None,
+            }]),
+            first: 0,
+            cursor: 0,
+        }
+    }
+
+    /// The step currently displayed
+    pub fn current(&self) -> &HistoryStep {
+        &self.steps[self.cursor - self.first]
+    }
+
+    /// The index of the step currently displayed, 0 being the initial step
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The index of the oldest step still kept
+    pub fn first(&self) -> usize {
+        self.first
+    }
+
+    /// The index of the last step computed
+    pub fn last(&self) -> usize {
+        self.first + self.steps.len() - 1
+    }
+
+    /// True if the step displayed is the last one computed by the executor
+    pub fn is_at_end(&self) -> bool {
+        self.cursor == self.last()
+    }
+
+    /// Record a new step computed by the executor and display it.
+    /// The oldest step is dropped if the history is full.
+    pub fn push(&mut self, step: TuringExecutionStep, state: u8, turing_machine: &TuringMachine) {
+        let transition = self.steps.back().and_then(|previous| fired_transition(previous, state, turing_machine));
+        self.steps.push_back(HistoryStep { step, state, transition });
+
+        if self.steps.len() > Constant::HISTORY_LIMIT {
+            self.steps.pop_front();
+            self.first += 1;
+        }
+        self.cursor = self.last();
+    }
+
+    /// Display the step at the index, limited to the steps still kept
+    pub fn go_to(&mut self, index: usize) {
+        self.cursor = index.clamp(self.first, self.last());
+    }
+}
+
+/// Find the transition going from the state of the previous step to the new state
+/// whose read characters are the ones under the heads of the ribbons in the previous step.
+fn fired_transition(previous: &HistoryStep, state: u8, turing_machine: &TuringMachine) -> Option<(u8, u8)> {
+    let read_ribbon = &previous.step.read_ribbon;
+    let heads: Vec<Option<char>> = std::iter::once(read_ribbon.chars_vec.get(read_ribbon.pointer as usize))
+        .chain(
+            previous.step.write_ribbons
+                .iter()
+                .map(|ribbon| ribbon.chars_vec.get(ribbon.pointer as usize)),
+        )
+        .map(|c| c.copied())
+        .collect();
+
+    turing_machine.states[previous.state as usize]
+        .transitions
+        .iter()
+        .position(|transition| {
+            transition.index_to_state == state
+                && transition.chars_read.iter().map(|c| Some(*c)).eq(heads.iter().copied())
+        })
+        .map(|index| (previous.state, index as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use turingrs::{parser::parse_turing_machine, turing_machine::TuringMachineExecutor};
+
+    use crate::ui::constant::Constant;
+
+    use super::History;
+
+    /// An executor on the empty word and the history of its initial step
+    fn execution(code: &str) -> (TuringMachineExecutor, History) {
+        let turing_machine = parse_turing_machine(code.to_string()).expect("the code can be parsed");
+        let (executor, initial_step) =
+            TuringMachineExecutor::new(turing_machine, String::new()).expect("the executor can be created");
+        let history = History::new(initial_step, executor.get_state_pointer());
+        (executor, history)
+    }
+
+    /// Compute the next steps of the executor and record them
+    fn push_steps(executor: &mut TuringMachineExecutor, history: &mut History, count: usize) {
+        for _ in 0..count {
+            let step = executor.as_iter().next().expect("the machine never stops");
+            history.push(step, executor.get_state_pointer(), &executor.turing_machine);
+        }
+    }
+
+    /// A machine moving right forever
+    const ENDLESS: &str = "q_a {ç, ç -> R, ç, R} q_a;";
+
+    #[test]
+    fn pushed_steps_are_displayed() {
+        let (mut executor, mut history) = execution(ENDLESS);
+        push_steps(&mut executor, &mut history, 3);
+
+        assert_eq!((history.first(), history.cursor(), history.last()), (0, 3, 3));
+        assert!(history.is_at_end());
+
+        history.go_to(1);
+        assert_eq!(history.cursor(), 1);
+        assert!(!history.is_at_end());
+
+        history.go_to(10);
+        assert_eq!(history.cursor(), 3);
+    }
+
+    #[test]
+    fn oldest_steps_are_dropped() {
+        let (mut executor, mut history) = execution(ENDLESS);
+        push_steps(&mut executor, &mut history, Constant::HISTORY_LIMIT + 5);
+
+        assert_eq!(history.first(), 6);
+        assert_eq!(history.last(), Constant::HISTORY_LIMIT + 5);
+
+        // the steps dropped can't be displayed anymore
+        history.go_to(0);
+        assert_eq!(history.cursor(), 6);
+    }
+
+    #[test]
+    fn fired_transition_is_found() {
+        let (mut executor, mut history) = execution("q_a {0, ç -> R, ç, N} q_b;\nq_a {ç, ç -> R, ç, R} q_b;");
+        assert_eq!(history.current().transition, None);
+
+        push_steps(&mut executor, &mut history, 1);
+        assert_eq!(history.current().state, 1);
+        assert_eq!(history.current().transition, Some((0, 1)));
+    }
+}
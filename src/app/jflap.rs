@@ -0,0 +1,489 @@
+use std::{collections::{BTreeSet, HashMap, HashSet}, fmt::Write, path::Path};
+
+use egui::{Pos2, vec2};
+use itertools::Itertools;
+use roxmltree::{Document, Node};
+use turingrs::{parser::parse_turing_machine, turing_state::{TuringDirection, TuringTransition}};
+
+use crate::{TuringApp, ui::constant::Constant};
+
+use super::{declaration::Declarations, machine, xml::escape};
+
+/// Extension of the JFLAP files
+pub const JFLAP_EXTENSION: &str = "jff";
+
+/// The blank symbol of the machines, JFLAP writes it as an empty symbol
+const BLANK: char = 'ç';
+
+/// True if the file is a JFLAP file
+pub fn is_jflap(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == JFLAP_EXTENSION)
+}
+
+/// A state of a JFLAP machine, identified by its id in the transitions
+struct JflapState {
+    id: String,
+    name: String,
+    position: Pos2,
+    is_initial: bool,
+    is_final: bool,
+}
+
+/// A transition of a JFLAP machine, with the symbols and the move of each tape.
+/// The moves are kept as written, `L`, `R` or `S`.
+struct JflapTransition {
+    from: String,
+    to: String,
+    read: Vec<char>,
+    write: Vec<char>,
+    moves: Vec<char>,
+}
+
+/// Import and export of the Turing machines of JFLAP, single-tape and multi-tape.
+///
+/// The tapes of JFLAP are all written, when the first one is never written it becomes the input ribbon
+/// and the next ones the ribbons of the machine. Otherwise each tape becomes a ribbon and two states
+/// are added before the initial one to copy the input on the first ribbon and move back to its start.
+/// The machines are exported with the input ribbon as the first tape.
+impl TuringApp {
+    /// Replace the code by the machine of the JFLAP file, then compile it and place the states as in JFLAP.
+    pub fn open_jflap(&mut self, content: &str) -> Result<(), String> {
+        let (tapes, states, transitions) = parse(content)?;
+        let (code, positions) = to_code(tapes, &states, &transitions)?;
+        self.load_code(code)?;
+
+        for state in self.states.values_mut() {
+            if let Some(position) = positions.get(&state.name) {
+                state.position = *position;
+            }
+        }
+        // every state has its position in the file
+        self.layout.cool();
+
+        Ok(())
+    }
+
+    /// The JFLAP file to save. The file holds the compiled machine and not the code,
+    /// so the code edited since the last compilation is compiled first and nothing is saved if it can't be.
+    pub fn jflap_content(&mut self) -> Result<String, String> {
+        if self.sync.code_edited.is_some() && self.sync.graph_edited.is_some() {
+            return Err("The code and the graph have both been edited, choose which one to keep".to_string());
+        }
+
+        let is_outdated = self.sync.code_edited.is_some() || parse_turing_machine(self.code.to_string()).is_err();
+        if is_outdated && !self.sync_from_code() {
+            return Err(self.diagnostic.as_ref().map(|diagnostic| diagnostic.message.to_string()).unwrap_or_default());
+        }
+
+        Ok(self.to_jflap())
+    }
+
+    /// The machine as a JFLAP file, with a tape for the input ribbon and a tape for each ribbon
+    pub fn to_jflap(&self) -> String {
+        let turing_machine = &self.turing.turing_machine;
+        let mut xml = String::new();
+
+        let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#);
+        let _ = writeln!(xml, "<structure>");
+        let _ = writeln!(xml, "\t<type>turing</type>");
+        let _ = writeln!(xml, "\t<tapes>{}</tapes>", turing_machine.k as usize + 1);
+        let _ = writeln!(xml, "\t<automaton>");
+
+        for (id, state) in turing_machine.states.iter().enumerate() {
+            let position = self.states.get(&(id as u8)).map_or(Pos2::ZERO, |state| state.position);
+
+            let _ = writeln!(xml, "\t\t<state id=\"{}\" name=\"{}\">", id, escape(&state.name));
+            let _ = writeln!(xml, "\t\t\t<x>{:.1}</x>", position.x);
+            let _ = writeln!(xml, "\t\t\t<y>{:.1}</y>", position.y);
+            if machine::is_initial(id as u8) {
+                let _ = writeln!(xml, "\t\t\t<initial/>");
+            }
+            if state.is_final {
+                let _ = writeln!(xml, "\t\t\t<final/>");
+            }
+            let _ = writeln!(xml, "\t\t</state>");
+        }
+
+        for (id, state) in turing_machine.states.iter().enumerate() {
+            for transition in state.transitions.iter() {
+                let _ = writeln!(xml, "\t\t<transition>");
+                let _ = writeln!(xml, "\t\t\t<from>{}</from>", id);
+                let _ = writeln!(xml, "\t\t\t<to>{}</to>", transition.index_to_state);
+
+                // the input ribbon is written with the symbol read to be left unchanged
+                let tapes = std::iter::once((transition.chars_read[0], transition.chars_read[0], &transition.move_read))
+                    .chain(
+                        transition.chars_read[1..]
+                            .iter()
+                            .zip(transition.chars_write.iter())
+                            .map(|(read, (write, direction))| (*read, *write, direction)),
+                    );
+
+                for (tape, (read, write, direction)) in tapes.enumerate() {
+                    let _ = writeln!(xml, "\t\t\t{}", symbol_element("read", tape + 1, read));
+                    let _ = writeln!(xml, "\t\t\t{}", symbol_element("write", tape + 1, write));
+                    let _ = writeln!(xml, "\t\t\t<move tape=\"{}\">{}</move>", tape + 1, jflap_move(direction));
+                }
+
+                let _ = writeln!(xml, "\t\t</transition>");
+            }
+        }
+
+        let _ = writeln!(xml, "\t</automaton>");
+        let _ = writeln!(xml, "</structure>");
+        xml
+    }
+}
+
+/// Read the number of tapes, the states and the transitions of a JFLAP Turing machine
+fn parse(content: &str) -> Result<(usize, Vec<JflapState>, Vec<JflapTransition>), String> {
+    let document = Document::parse(content).map_err(|error| error.to_string())?;
+    let structure = document.root_element();
+
+    if child_text(structure, "type") != Some("turing") {
+        return Err("The file is not a Turing machine".to_string());
+    }
+
+    // the single-tape machines don't write the number of tapes
+    let tapes: usize = match child_text(structure, "tapes") {
+        Some(tapes) => tapes.trim().parse().map_err(|_| format!("Invalid number of tapes '{}'", tapes))?,
+        None => 1,
+    };
+    if tapes == 0 {
+        return Err("The machine has no tape".to_string());
+    }
+
+    let automaton = structure
+        .children()
+        .find(|node| node.has_tag_name("automaton"))
+        .ok_or("The file has no automaton")?;
+
+    if automaton.children().any(|node| node.has_tag_name("block")) {
+        return Err("The building blocks are not supported".to_string());
+    }
+
+    let states = automaton
+        .children()
+        .filter(|node| node.has_tag_name("state"))
+        .map(|node| {
+            let id = node.attribute("id").ok_or("A state has no id")?.to_string();
+            let coordinate = |name: &str| child_text(node, name).and_then(|value| value.trim().parse::<f32>().ok()).unwrap_or(0.0);
+
+            Ok(JflapState {
+                name: node.attribute("name").unwrap_or_default().to_string(),
+                position: Pos2::new(coordinate("x"), coordinate("y")),
+                is_initial: node.children().any(|child| child.has_tag_name("initial")),
+                is_final: node.children().any(|child| child.has_tag_name("final")),
+                id,
+            })
+        })
+        .collect::<Result<Vec<JflapState>, String>>()?;
+
+    let transitions = automaton
+        .children()
+        .filter(|node| node.has_tag_name("transition"))
+        .map(|node| parse_transition(node, tapes))
+        .collect::<Result<Vec<JflapTransition>, String>>()?;
+
+    Ok((tapes, states, transitions))
+}
+
+/// Read the symbols and the moves of each tape of a transition,
+/// the tapes not written read and write a blank without moving
+fn parse_transition(node: Node, tapes: usize) -> Result<JflapTransition, String> {
+    let mut transition = JflapTransition {
+        from: child_text(node, "from").ok_or("A transition has no source")?.trim().to_string(),
+        to: child_text(node, "to").ok_or("A transition has no target")?.trim().to_string(),
+        read: vec![BLANK; tapes],
+        write: vec![BLANK; tapes],
+        moves: vec!['S'; tapes],
+    };
+
+    for child in node.children().filter(|child| child.is_element()) {
+        let tape: usize = child.attribute("tape").map_or(Ok(1), |tape| tape.parse()).map_err(|_| "Invalid tape number".to_string())?;
+        if tape == 0 || tape > tapes {
+            return Err(format!("The machine has no tape {}", tape));
+        }
+
+        let text = child.text().unwrap_or_default();
+        match child.tag_name().name() {
+            "read" => transition.read[tape - 1] = symbol(text)?,
+            "write" => transition.write[tape - 1] = symbol(text)?,
+            "move" => match text.trim() {
+                m @ ("L" | "R" | "S") => transition.moves[tape - 1] = m.chars().next().unwrap(),
+                m => return Err(format!("Invalid move '{}'", m)),
+            },
+            _ => {}
+        }
+    }
+
+    Ok(transition)
+}
+
+/// Write the JFLAP machine as code, with the position of each state by name
+fn to_code(
+    tapes: usize,
+    states: &[JflapState],
+    transitions: &[JflapTransition],
+) -> Result<(String, HashMap<String, Pos2>), String> {
+    let names = state_names(states);
+    let initial = states.iter().find(|state| state.is_initial).or(states.first()).ok_or("The machine has no state")?;
+
+    let name = |id: &String| names.get(id).cloned().ok_or(format!("No state has the id {}", id));
+    let mut positions: HashMap<String, Pos2> = states.iter().map(|state| (names[&state.id].to_string(), state.position)).collect();
+    let mut rules: Vec<(String, TuringTransition, String)> = vec![];
+    let mut initial_name = names[&initial.id].to_string();
+
+    let is_input_read_only = tapes > 1 && transitions.iter().all(|transition| transition.write[0] == transition.read[0]);
+
+    for transition in transitions {
+        let turing_transition = if is_input_read_only {
+            TuringTransition::new(
+                transition.read.clone(),
+                direction(transition.moves[0]),
+                transition.write[1..].iter().zip(transition.moves[1..].iter()).map(|(c, m)| (*c, direction(*m))).collect(),
+            )
+        } else {
+            // the input ribbon is not read once copied
+            TuringTransition::new(
+                std::iter::once(BLANK).chain(transition.read.iter().copied()).collect(),
+                TuringDirection::None,
+                transition.write.iter().zip(transition.moves.iter()).map(|(c, m)| (*c, direction(*m))).collect(),
+            )
+        };
+
+        rules.push((name(&transition.from)?, turing_transition, name(&transition.to)?));
+    }
+
+    if !is_input_read_only {
+        let used: HashSet<String> = names.values().cloned().collect();
+        let copy = unique_name("copy", &used);
+        let rewind = unique_name("rewind", &used);
+
+        // the symbols of the input are the symbols read on the first tape, the first tape can also hold the symbols written
+        let input: BTreeSet<char> = transitions.iter().map(|transition| transition.read[0]).filter(|c| *c != BLANK).collect();
+        let tape: BTreeSet<char> = transitions
+            .iter()
+            .flat_map(|transition| [transition.read[0], transition.write[0]])
+            .filter(|c| *c != BLANK)
+            .collect();
+
+        // the other ribbons are left blank
+        let rule = |read_input: char, read_tape: char, write: char, tape_direction: TuringDirection, input_direction: TuringDirection| {
+            TuringTransition::new(
+                [read_input, read_tape].into_iter().chain(std::iter::repeat_n(BLANK, tapes - 1)).collect(),
+                input_direction,
+                std::iter::once((write, tape_direction))
+                    .chain(std::iter::repeat_with(|| (BLANK, TuringDirection::None)).take(tapes - 1))
+                    .collect(),
+            )
+        };
+
+        for c in input {
+            rules.push((copy.to_string(), rule(c, BLANK, c, TuringDirection::Right, TuringDirection::Right), copy.to_string()));
+        }
+        rules.push((copy.to_string(), rule(BLANK, BLANK, BLANK, TuringDirection::Left, TuringDirection::None), rewind.to_string()));
+
+        for c in tape {
+            rules.push((rewind.to_string(), rule(BLANK, c, c, TuringDirection::Left, TuringDirection::None), rewind.to_string()));
+        }
+        rules.push((rewind.to_string(), rule(BLANK, BLANK, BLANK, TuringDirection::Right, TuringDirection::None), initial_name.to_string()));
+
+        // the added states are placed on the left of the initial state
+        positions.insert(copy.to_string(), initial.position - vec2(Constant::LAYOUT_SPACING * 2.0, 0.0));
+        positions.insert(rewind.to_string(), initial.position - vec2(Constant::LAYOUT_SPACING, 0.0));
+        initial_name = copy;
+    }
+
+    let accepting: Vec<String> = states.iter().filter(|state| state.is_final).map(|state| names[&state.id].to_string()).collect();
+    let declarations = Declarations {
+        states: positions.keys().sorted().cloned().collect(),
+        initial: Some(initial_name),
+        accepting: (!accepting.is_empty()).then_some(accepting),
+    };
+
+    let code = std::iter::once(declarations.to_code())
+        .chain(rules.iter().map(|(from, transition, to)| format!("q_{} {{{}}} q_{};", from, transition, to)))
+        .join("\n");
+
+    Ok((code, positions))
+}
+
+/// The name of each state by id, the characters that can't be used in a name are replaced by `_`.
+/// The states without name are named by their id, and the names already used are made unique.
+fn state_names(states: &[JflapState]) -> HashMap<String, String> {
+    let mut used: HashSet<String> = HashSet::new();
+    let mut names: HashMap<String, String> = HashMap::new();
+
+    for state in states {
+        let name = if state.name.is_empty() { format!("q{}", state.id) } else { state.name.to_string() };
+        let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+
+        let name = unique_name(&name, &used);
+        used.insert(name.to_string());
+        names.insert(state.id.to_string(), name);
+    }
+
+    names
+}
+
+/// The name followed by as many `_` as needed to not be used yet
+fn unique_name(name: &str, used: &HashSet<String>) -> String {
+    let mut name = name.to_string();
+    while used.contains(&name) {
+        name.push('_');
+    }
+    name
+}
+
+/// The text of the child element with the tag
+fn child_text<'a>(node: Node<'a, 'a>, tag: &str) -> Option<&'a str> {
+    node.children().find(|child| child.has_tag_name(tag)).map(|child| child.text().unwrap_or_default())
+}
+
+/// The symbol of a tape, the empty symbol is the blank.
+/// The symbols which can't be written in the code are rejected.
+fn symbol(text: &str) -> Result<char, String> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => Ok(BLANK),
+        (Some(c), None) => machine::validate_symbol(c).map(|_| c),
+        _ => Err(format!("The symbol '{}' is not a single character", text)),
+    }
+}
+
+fn direction(jflap_move: char) -> TuringDirection {
+    match jflap_move {
+        'L' => TuringDirection::Left,
+        'R' => TuringDirection::Right,
+        _ => TuringDirection::None,
+    }
+}
+
+fn jflap_move(direction: &TuringDirection) -> &'static str {
+    match direction {
+        TuringDirection::Left => "L",
+        TuringDirection::Right => "R",
+        TuringDirection::None => "S",
+    }
+}
+
+/// A symbol of a tape, the blank is written as an empty element
+fn symbol_element(tag: &str, tape: usize, c: char) -> String {
+    if c == BLANK {
+        format!("<{} tape=\"{}\"/>", tag, tape)
+    } else {
+        format!("<{} tape=\"{}\">{}</{}>", tag, tape, escape(&c.to_string()), tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TuringApp;
+
+    use super::{parse, to_code};
+
+    /// A single-tape machine replacing the `a` by `b` until the first blank
+    const SINGLE_TAPE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<structure>
+    <type>turing</type>
+    <automaton>
+        <state id="0" name="start"><x>10.0</x><y>20.0</y><initial/></state>
+        <state id="1" name="end"><x>110.0</x><y>20.0</y><final/></state>
+        <transition><from>0</from><to>0</to><read>a</read><write>b</write><move>R</move></transition>
+        <transition><from>0</from><to>1</to><read/><write/><move>S</move></transition>
+    </automaton>
+</structure>"#;
+
+    /// A machine with 2 tapes copying the first one on the second one, the first tape is never written
+    const TWO_TAPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<structure>
+    <type>turing</type>
+    <tapes>2</tapes>
+    <automaton>
+        <state id="0" name="copy"><x>0</x><y>0</y><initial/></state>
+        <state id="1" name="done"><x>100</x><y>0</y><final/></state>
+        <transition>
+            <from>0</from><to>0</to>
+            <read tape="1">a</read><write tape="1">a</write><move tape="1">R</move>
+            <read tape="2"></read><write tape="2">a</write><move tape="2">R</move>
+        </transition>
+        <transition>
+            <from>0</from><to>1</to>
+            <read tape="1"/><write tape="1"/><move tape="1">S</move>
+            <read tape="2"/><write tape="2"/><move tape="2">L</move>
+        </transition>
+    </automaton>
+</structure>"#;
+
+    fn code(content: &str) -> String {
+        let (tapes, states, transitions) = parse(content).unwrap();
+        to_code(tapes, &states, &transitions).unwrap().0
+    }
+
+    #[test]
+    fn single_tape_copies_the_input_first() {
+        let code = code(SINGLE_TAPE);
+
+        assert!(code.contains("// initial: q_copy"));
+        assert!(code.contains("// accepting: q_end"));
+        assert!(code.contains("q_copy {a, ç -> R, a, R} q_copy;"));
+        assert!(code.contains("q_copy {ç, ç -> N, ç, L} q_rewind;"));
+        assert!(code.contains("q_rewind {ç, a -> N, a, L} q_rewind;"));
+        assert!(code.contains("q_rewind {ç, b -> N, b, L} q_rewind;"));
+        assert!(code.contains("q_rewind {ç, ç -> N, ç, R} q_start;"));
+        // the input ribbon is not read anymore
+        assert!(code.contains("q_start {ç, a -> N, b, R} q_start;"));
+        assert!(code.contains("q_start {ç, ç -> N, ç, N} q_end;"));
+    }
+
+    #[test]
+    fn first_tape_never_written_is_the_input() {
+        let code = code(TWO_TAPES);
+
+        assert!(code.contains("// initial: q_copy"));
+        assert!(!code.contains("q_rewind"));
+        assert!(code.contains("q_copy {a, ç -> R, a, R} q_copy;"));
+        assert!(code.contains("q_copy {ç, ç -> N, ç, L} q_done;"));
+    }
+
+    #[test]
+    fn empty_symbols_are_blanks() {
+        let (_, _, transitions) = parse(TWO_TAPES).unwrap();
+
+        assert_eq!(transitions[0].read, vec!['a', 'ç']);
+        assert_eq!(transitions[1].read, vec!['ç', 'ç']);
+        assert_eq!(transitions[1].write, vec!['ç', 'ç']);
+    }
+
+    #[test]
+    fn separators_are_rejected() {
+        let content = SINGLE_TAPE.replace("<read>a</read>", "<read>;</read>");
+
+        assert!(parse(&content).is_err());
+    }
+
+    #[test]
+    fn exported_machine_is_imported() {
+        let mut app = TuringApp::compiled("// initial: q_b\n// accepting: q_c\n\nq_a {0, ç -> R, 1, L} q_b;\nq_b {1, ç -> L, ç, N} q_a;\nq_b {ç, ç -> N, ç, N} q_c;");
+        let turing_machine = app.turing.turing_machine.clone();
+        let positions: Vec<(String, egui::Pos2)> = app.states.values().map(|state| (state.name.to_string(), state.position)).collect();
+
+        let jflap = app.to_jflap();
+        let mut imported = TuringApp::default();
+        imported.open_jflap(&jflap).unwrap();
+
+        let imported_machine = &imported.turing.turing_machine;
+        assert_eq!(imported_machine.k, turing_machine.k);
+        assert_eq!(imported_machine.states.len(), turing_machine.states.len());
+        for (state, imported_state) in turing_machine.states.iter().zip(imported_machine.states.iter()) {
+            assert_eq!(state.name, imported_state.name);
+            assert_eq!(state.is_final, imported_state.is_final);
+            assert_eq!(state.transitions, imported_state.transitions);
+        }
+        for (name, position) in positions {
+            let state = imported.states.values().find(|state| state.name == name).unwrap();
+            assert!((state.position - position).length() < 0.1);
+        }
+    }
+}
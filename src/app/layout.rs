@@ -0,0 +1,369 @@
+use std::{collections::{HashMap, VecDeque}, f32::consts::TAU};
+
+use egui::{Color32, Pos2, Vec2, vec2};
+use turingrs::turing_machine::TuringMachine;
+
+use crate::{TuringApp, ui::constant::Constant};
+
+/// The line along which states are aligned
+#[derive(Clone, Copy)]
+pub enum Alignment {
+    Horizontal,
+    Vertical,
+}
+
+/// The edits of the layout of the graph, the turing machine is not modified
+impl TuringApp {
+    /// Align the states on their average position, the move can be undone
+    pub fn align_states(&mut self, ids: &[u8], alignment: Alignment) {
+        let positions: Vec<Pos2> = ids.iter().filter_map(|id| self.states.get(id)).map(|state| state.position).collect();
+        if positions.len() < 2 {
+            return;
+        }
+
+        let center = centroid(&positions);
+
+        self.start_move(ids);
+        for id in ids {
+            if let Some(state) = self.states.get_mut(id) {
+                match alignment {
+                    Alignment::Horizontal => state.position.y = center.y,
+                    Alignment::Vertical => state.position.x = center.x,
+                }
+            }
+        }
+        self.end_move();
+    }
+
+    /// Change the color of the states
+    pub fn recolor_states(&mut self, ids: &[u8], color: Color32) {
+        for id in ids {
+            if let Some(state) = self.states.get_mut(id) {
+                state.color = color;
+            }
+        }
+        self.is_dirty = true;
+    }
+
+    /// Compute the layout chosen and start moving the states to it.
+    /// The graph stays centered where it is, the move can be undone once finished.
+    pub fn relayout(&mut self) {
+        let layers = layers(&self.turing.turing_machine);
+
+        let targets = match self.layout.kind {
+            LayoutKind::Layered => layered(&self.turing.turing_machine, layers),
+            LayoutKind::Circular => circular(&layers.concat()),
+            LayoutKind::Grid => grid(&layers.concat()),
+        };
+
+        self.animate_to(targets);
+    }
+
+    /// Move the states toward the positions, the center of the states moved stays in place.
+    /// The move can be undone once the states are placed.
+    pub fn animate_to(&mut self, targets: HashMap<u8, Pos2>) {
+        let targets: HashMap<u8, Pos2> = targets.into_iter().filter(|(id, _)| self.states.contains_key(id)).collect();
+        let ids: Vec<u8> = targets.keys().copied().collect();
+        let current: Vec<Pos2> = ids.iter().map(|id| self.states[id].position).collect();
+        let offset = centroid(&current) - centroid(&targets.values().copied().collect::<Vec<Pos2>>());
+
+        self.start_move(&ids);
+        self.layout.targets = targets
+            .into_iter()
+            .map(|(id, position)| (id, position + offset))
+            .collect();
+    }
+
+    /// Move the states toward their position in the layout.
+    /// Return true while the states are moving.
+    pub fn animate_layout(&mut self, dt: f32) -> bool {
+        if self.layout.targets.is_empty() {
+            return false;
+        }
+
+        let progress = (dt * Constant::LAYOUT_ANIMATION_SPEED).min(1.0);
+        let mut is_placed = true;
+
+        for (id, target) in self.layout.targets.iter() {
+            if let Some(state) = self.states.get_mut(id) {
+                let delta = *target - state.position;
+                if delta.length() < Constant::LAYOUT_SNAP_DISTANCE {
+                    state.position = *target;
+                } else {
+                    state.position += delta * progress;
+                    is_placed = false;
+                }
+            }
+        }
+
+        // the force simulation is cooled down to keep the states where the layout placed them
+        if is_placed {
+            self.layout.stop();
+            self.layout.cool();
+            self.end_move();
+        }
+
+        true
+    }
+}
+
+/// The states grouped by their distance from the initial state, following the transitions.
+/// The states not reachable are placed in the next layers, from the first of them by index.
+fn layers(turing_machine: &TuringMachine) -> Vec<Vec<u8>> {
+    let state_count = turing_machine.states.len();
+    let mut layer_of: Vec<Option<usize>> = vec![None; state_count];
+    let mut layers: Vec<Vec<u8>> = vec![];
+
+    for root in 0..state_count {
+        if layer_of[root].is_some() {
+            continue;
+        }
+
+        let first_layer = layers.len();
+        layer_of[root] = Some(first_layer);
+        let mut queue = VecDeque::from([root]);
+
+        while let Some(index) = queue.pop_front() {
+            let layer = layer_of[index].unwrap();
+            if layers.len() <= layer {
+                layers.push(vec![]);
+            }
+            layers[layer].push(index as u8);
+
+            for transition in turing_machine.states[index].transitions.iter() {
+                let target = transition.index_to_state as usize;
+                if layer_of[target].is_none() {
+                    layer_of[target] = Some(layer + 1);
+                    queue.push_back(target);
+                }
+            }
+        }
+    }
+
+    layers
+}
+
+/// The states adjacent to each state, whatever the direction of the transitions
+fn neighbours(turing_machine: &TuringMachine) -> HashMap<u8, Vec<u8>> {
+    let mut neighbours: HashMap<u8, Vec<u8>> = HashMap::new();
+
+    for (index, state) in turing_machine.states.iter().enumerate() {
+        for transition in state.transitions.iter().filter(|transition| transition.index_to_state != index as u8) {
+            neighbours.entry(index as u8).or_default().push(transition.index_to_state);
+            neighbours.entry(transition.index_to_state).or_default().push(index as u8);
+        }
+    }
+
+    neighbours
+}
+
+/// The layers placed in columns from the left, the order of the states in each layer
+/// is chosen to reduce the crossing of the transitions by placing each state
+/// at the average height of its neighbours in the adjacent layers.
+fn layered(turing_machine: &TuringMachine, mut layers: Vec<Vec<u8>>) -> HashMap<u8, Pos2> {
+    let neighbours = neighbours(turing_machine);
+
+    for _ in 0..Constant::LAYOUT_SWEEPS {
+        for layer in 1..layers.len() {
+            order_by_neighbours(&mut layers, layer, layer - 1, &neighbours);
+        }
+        for layer in (0..layers.len().saturating_sub(1)).rev() {
+            order_by_neighbours(&mut layers, layer, layer + 1, &neighbours);
+        }
+    }
+
+    layers
+        .iter()
+        .enumerate()
+        .flat_map(|(column, layer)| {
+            let height = (layer.len() - 1) as f32;
+            layer.iter().enumerate().map(move |(row, id)| {
+                (*id, (vec2(column as f32, row as f32 - height / 2.0) * Constant::LAYOUT_SPACING).to_pos2())
+            })
+        })
+        .collect()
+}
+
+/// Sort a layer by the average index of the neighbours of each state in the reference layer.
+/// The states without neighbours there keep their index.
+fn order_by_neighbours(layers: &mut [Vec<u8>], layer: usize, reference: usize, neighbours: &HashMap<u8, Vec<u8>>) {
+    let reference_index: HashMap<u8, usize> = layers[reference].iter().enumerate().map(|(index, id)| (*id, index)).collect();
+
+    let barycenter = |index: usize, id: &u8| {
+        let indexes: Vec<usize> = neighbours
+            .get(id)
+            .into_iter()
+            .flatten()
+            .filter_map(|neighbour| reference_index.get(neighbour).copied())
+            .collect();
+
+        match indexes.len() {
+            0 => index as f32,
+            count => indexes.iter().sum::<usize>() as f32 / count as f32,
+        }
+    };
+
+    let mut ordered: Vec<(f32, u8)> = layers[layer].iter().enumerate().map(|(index, id)| (barycenter(index, id), *id)).collect();
+    ordered.sort_by(|a, b| a.0.total_cmp(&b.0));
+    layers[layer] = ordered.into_iter().map(|(_, id)| id).collect();
+}
+
+/// The states placed on a circle in order, starting from its left
+fn circular(order: &[u8]) -> HashMap<u8, Pos2> {
+    let radius = (order.len() as f32 * Constant::LAYOUT_SPACING / TAU).max(Constant::LAYOUT_SPACING);
+
+    order
+        .iter()
+        .enumerate()
+        .map(|(index, id)| {
+            let angle = TAU * index as f32 / order.len() as f32;
+            (*id, Pos2::ZERO - Vec2::angled(angle) * radius)
+        })
+        .collect()
+}
+
+/// The states placed in order on the rows of a square grid
+fn grid(order: &[u8]) -> HashMap<u8, Pos2> {
+    let columns = (order.len() as f32).sqrt().ceil().max(1.0) as usize;
+
+    order
+        .iter()
+        .enumerate()
+        .map(|(index, id)| (*id, (vec2((index % columns) as f32, (index / columns) as f32) * Constant::LAYOUT_SPACING).to_pos2()))
+        .collect()
+}
+
+/// The average of the positions
+pub fn centroid(positions: &[Pos2]) -> Pos2 {
+    match positions.len() {
+        0 => Pos2::ZERO,
+        count => positions.iter().fold(Pos2::ZERO, |sum, position| sum + position.to_vec2()) / count as f32,
+    }
+}
+
+/// The automatic layouts of the graph, computed from the transitions of the machine
+#[derive(Clone, Copy, PartialEq)]
+pub enum LayoutKind {
+    /// The states in columns by distance from the initial state
+    Layered,
+    Circular,
+    Grid,
+}
+
+impl LayoutKind {
+    pub const ALL: [LayoutKind; 3] = [LayoutKind::Layered, LayoutKind::Circular, LayoutKind::Grid];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LayoutKind::Layered => "Layered",
+            LayoutKind::Circular => "Circular",
+            LayoutKind::Grid => "Grid",
+        }
+    }
+}
+
+/// The automatic layout chosen and the positions the states are moving to
+pub struct LayoutState {
+    pub kind: LayoutKind,
+    /// The force simulation doesn't move the states when frozen
+    pub is_frozen: bool,
+    /// How much the force simulation moves the states, it cools down until they stop moving
+    pub temperature: f32,
+    targets: HashMap<u8, Pos2>,
+}
+
+impl Default for LayoutState {
+    fn default() -> Self {
+        Self {
+            kind: LayoutKind::Layered,
+            is_frozen: false,
+            temperature: 1.0,
+            targets: HashMap::new(),
+        }
+    }
+}
+
+impl LayoutState {
+    /// Stop moving the states, their index may have changed
+    pub fn stop(&mut self) {
+        self.targets.clear();
+    }
+
+    /// Restart the force simulation after the graph changed
+    pub fn reheat(&mut self) {
+        self.temperature = 1.0;
+    }
+
+    /// Stop the force simulation, the states stay where they have been placed
+    pub fn cool(&mut self) {
+        self.temperature = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use turingrs::{parser::parse_turing_machine, turing_machine::TuringMachine};
+
+    use crate::{app::declaration::Declarations, ui::constant::Constant};
+
+    use super::{layered, layers};
+
+    /// A tree from `q_a` and a state without transitions
+    const CODE: &str = "// states: q_e
+q_a {0, ç -> R, ç, N} q_b;
+q_a {1, ç -> R, ç, N} q_c;
+q_b {0, ç -> R, ç, N} q_d;
+q_d {0, ç -> L, ç, N} q_a;
+";
+
+    fn machine() -> TuringMachine {
+        let mut turing_machine = parse_turing_machine(CODE.to_string()).expect("the code can be parsed");
+        Declarations::parse(CODE).apply(&mut turing_machine);
+        turing_machine
+    }
+
+    /// The names of the states of each layer, sorted in each layer
+    fn names(turing_machine: &TuringMachine, layers: &[Vec<u8>]) -> Vec<Vec<String>> {
+        layers
+            .iter()
+            .map(|layer| {
+                let mut names: Vec<String> = layer.iter().map(|id| turing_machine.states[*id as usize].name.to_string()).collect();
+                names.sort();
+                names
+            })
+            .collect()
+    }
+
+    #[test]
+    fn layers_follow_the_distance_from_the_initial_state() {
+        let turing_machine = machine();
+
+        assert_eq!(names(&turing_machine, &layers(&turing_machine)), vec![
+            vec!["a".to_string()],
+            vec!["b".to_string(), "c".to_string()],
+            vec!["d".to_string()],
+            vec!["e".to_string()],
+        ]);
+    }
+
+    #[test]
+    fn layers_are_placed_in_centered_columns() {
+        let turing_machine = machine();
+        let layers = layers(&turing_machine);
+        let positions = layered(&turing_machine, layers.clone());
+
+        assert_eq!(positions.len(), turing_machine.states.len());
+        for (column, layer) in layers.iter().enumerate() {
+            let x = column as f32 * Constant::LAYOUT_SPACING;
+            assert!(layer.iter().all(|id| positions[id].x == x));
+            assert_eq!(layer.iter().map(|id| positions[id].y).sum::<f32>(), 0.0);
+        }
+    }
+
+    #[test]
+    fn layered_layout_is_deterministic() {
+        let turing_machine = machine();
+
+        assert_eq!(layered(&turing_machine, layers(&turing_machine)), layered(&turing_machine, layers(&turing_machine)));
+    }
+}
@@ -0,0 +1,136 @@
+use itertools::Itertools;
+use turingrs::{turing_machine::TuringMachine, turing_state::TuringTransition};
+
+/// Add a state without transitions to the machine and return its index.
+/// The new state is a copy of the first one emptied, as turingrs only create states through rules.
+pub fn add_state(turing_machine: &mut TuringMachine, name: &str) -> u8 {
+    let mut state = turing_machine.states[0].clone();
+    state.name = name.to_string();
+    state.is_final = false;
+    state.transitions.clear();
+
+    turing_machine.states.push(state);
+    let index = (turing_machine.states.len() - 1) as u8;
+    turing_machine.name_index_hashmap.insert(name.to_string(), index);
+
+    index
+}
+
+/// True if the state is the initial one, the initial state is always the first one
+pub fn is_initial(id: u8) -> bool {
+    id == 0
+}
+
+/// Make the state the initial one by swapping it with the state at index 0.
+pub fn set_initial(turing_machine: &mut TuringMachine, index: u8) {
+    swap_states(turing_machine, 0, index);
+}
+
+/// Swap the index of two states, the transitions targeting them are updated.
+pub fn swap_states(turing_machine: &mut TuringMachine, a: u8, b: u8) {
+    if a == b {
+        return;
+    }
+
+    turing_machine.states.swap(a as usize, b as usize);
+
+    for state in turing_machine.states.iter_mut() {
+        for transition in state.transitions.iter_mut() {
+            if transition.index_to_state == a {
+                transition.index_to_state = b;
+            } else if transition.index_to_state == b {
+                transition.index_to_state = a;
+            }
+        }
+    }
+
+    reindex_names(turing_machine);
+}
+
+/// Rebuild the index of the states by name from their position in the machine
+pub fn reindex_names(turing_machine: &mut TuringMachine) {
+    turing_machine.name_index_hashmap = turing_machine
+        .states
+        .iter()
+        .enumerate()
+        .map(|(index, state)| (state.name.to_string(), index as u8))
+        .collect();
+}
+
+/// Check a name can be used for a state, the names are written after the `q_` prefix in the code
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("The name of a state can't be empty".to_string());
+    }
+
+    match name.chars().find(|c| !c.is_ascii_alphanumeric() && *c != '_') {
+        Some(c) => Err(format!("The character '{}' can't be used in the name of a state", c)),
+        None => Ok(()),
+    }
+}
+
+/// The characters separating the parts of a rule in the code
+const SEPARATORS: [char; 5] = [',', '|', '{', '}', ';'];
+
+/// Check a character can be read or written by a transition, the separators and whitespaces can't be written in the code
+pub fn validate_symbol(c: char) -> Result<(), String> {
+    if SEPARATORS.contains(&c) || c.is_whitespace() {
+        return Err(format!("The symbol '{}' can't be written in the code", c));
+    }
+
+    Ok(())
+}
+
+/// The first name made of the prefix followed by a number which is not used by a state of the machine
+pub fn unique_name(turing_machine: &TuringMachine, prefix: &str) -> String {
+    (0..)
+        .map(|n| format!("{}{}", prefix, n))
+        .find(|name| !turing_machine.name_index_hashmap.contains_key(name))
+        .unwrap()
+}
+
+/// Rename a state and update the index of the states by name
+pub fn rename_state(turing_machine: &mut TuringMachine, index: u8, name: &str) {
+    turing_machine.states[index as usize].name = name.to_string();
+    reindex_names(turing_machine);
+}
+
+/// Remove a state and the transitions targeting it.
+/// The states after it are shifted and the transitions targeting them are updated.
+pub fn remove_state(turing_machine: &mut TuringMachine, index: u8) {
+    turing_machine.states.remove(index as usize);
+
+    for state in turing_machine.states.iter_mut() {
+        state.transitions.retain(|transition| transition.index_to_state != index);
+
+        for transition in state.transitions.iter_mut() {
+            if transition.index_to_state > index {
+                transition.index_to_state -= 1;
+            }
+        }
+    }
+
+    reindex_names(turing_machine);
+}
+
+/// Remove a transition of a state, the transitions after it are shifted
+pub fn remove_transition(turing_machine: &mut TuringMachine, state: u8, index: u8) {
+    turing_machine.states[state as usize].transitions.remove(index as usize);
+}
+
+/// The transitions grouped by source and target state, ordered by source then by target
+pub fn edges(turing_machine: &TuringMachine) -> Vec<(u8, u8, Vec<&TuringTransition>)> {
+    turing_machine
+        .states
+        .iter()
+        .enumerate()
+        .flat_map(|(from, state)| {
+            state.transitions
+                .iter()
+                .into_group_map_by(|transition| transition.index_to_state)
+                .into_iter()
+                .sorted_by_key(|(to, _)| *to)
+                .map(move |(to, transitions)| (from as u8, to, transitions))
+        })
+        .collect()
+}
@@ -0,0 +1,189 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use egui::{Color32, Pos2, Rect};
+use serde::{Deserialize, Serialize};
+
+use crate::{TuringApp, ui::turing::CurveOffset};
+
+use super::{Diagnostic, jflap::is_jflap};
+
+/// Extension of the project files, the other files are read as code
+pub const PROJECT_EXTENSION: &str = "tmproj";
+
+/// Layout of a state, the state is identified by its name in the code
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StateLayout {
+    pub name: String,
+    pub position: Pos2,
+    pub color: Color32,
+    /// The offsets of the curves from the state, by name of the target state
+    #[serde(default)]
+    pub curves: HashMap<String, CurveOffset>,
+}
+
+/// A project hold the code of the machine with the layout of its graph and the last input
+#[derive(Serialize, Deserialize)]
+pub struct Project {
+    pub code: String,
+    pub word_input: String,
+    pub graph_rect: Rect,
+    pub states: Vec<StateLayout>,
+    /// The force simulation doesn't move the states
+    #[serde(default)]
+    pub is_frozen: bool,
+}
+
+/// What is restored when the application is launched again
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub project: Project,
+    pub file_path: Option<PathBuf>,
+    pub is_dirty: bool,
+    pub speed: f32,
+}
+
+/// True if the file is a project file
+pub fn is_project(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == PROJECT_EXTENSION)
+}
+
+impl TuringApp {
+    /// Create a project from the current code and graph
+    pub fn to_project(&self) -> Project {
+        Project {
+            code: self.code.to_string(),
+            word_input: self.word_input.to_string(),
+            graph_rect: self.graph_rect,
+            states: self.layouts(),
+            is_frozen: self.layout.is_frozen,
+        }
+    }
+
+    /// The layout of every state of the graph
+    pub fn layouts(&self) -> Vec<StateLayout> {
+        self.states
+            .values()
+            .map(|state| StateLayout {
+                name: state.name.to_string(),
+                position: state.position,
+                color: state.color,
+                curves: state.curves.clone(),
+            })
+            .collect()
+    }
+
+    /// Replace the code by the project one, then compile it and place the states as saved.
+    /// An empty code is not compiled to keep the default machine.
+    pub fn load_project(&mut self, project: Project) -> Result<(), String> {
+        self.word_input = project.word_input;
+        self.graph_rect = project.graph_rect;
+        self.layout.is_frozen = project.is_frozen;
+        if project.code.trim().is_empty() {
+            self.code = project.code;
+        } else {
+            self.load_code(project.code)?;
+        }
+        self.apply_layout(&project.states);
+        Ok(())
+    }
+
+    /// Replace the code by the one read from a file and compile it.
+    /// If the code can't be compiled, it is kept to be fixed but not attached to the file anymore.
+    pub fn load_code(&mut self, code: String) -> Result<(), String> {
+        self.code = code;
+        if self.sync_from_code() {
+            return Ok(());
+        }
+
+        self.detach_file();
+        Err(self.diagnostic.as_ref().map(|diagnostic| diagnostic.message.to_string()).unwrap_or_default())
+    }
+
+    /// Forget the file opened, so the code is not saved over it without asking
+    pub fn detach_file(&mut self) {
+        self.file_path = None;
+        self.is_dirty = true;
+    }
+
+    /// Move, color and bend the curves of the states with the same name as the layouts.
+    /// The force simulation is stopped if every state is restored, so they stay where they were saved.
+    pub fn apply_layout(&mut self, layouts: &[StateLayout]) {
+        let mut is_restored = true;
+
+        for state in self.states.values_mut() {
+            match layouts.iter().find(|layout| layout.name == state.name) {
+                Some(layout) => {
+                    state.position = layout.position;
+                    state.color = layout.color;
+                    state.curves = layout.curves.clone();
+                }
+                None => is_restored = false,
+            }
+        }
+
+        if is_restored {
+            self.layout.cool();
+        }
+    }
+
+    /// Create a session from the current state of the application
+    pub fn to_session(&self) -> Session {
+        Session {
+            project: self.to_project(),
+            file_path: self.file_path.to_owned(),
+            is_dirty: self.is_dirty,
+            speed: self.playback.speed,
+        }
+    }
+
+    /// Resume the application where the session left it
+    pub fn load_session(&mut self, session: Session) {
+        // the code is restored even if it can't be compiled, as it was being edited
+        let _ = self.load_project(session.project);
+        self.file_path = session.file_path;
+        self.is_dirty = session.is_dirty;
+        self.playback.speed = session.speed;
+    }
+
+    /// The content to write in the file, a project for the project files, a JFLAP machine for the JFLAP files,
+    /// the code otherwise
+    pub fn file_content(&mut self, path: &Path) -> Result<String, String> {
+        if is_project(path) {
+            Ok(serde_json::to_string_pretty(&self.to_project()).expect("cannot serialize project"))
+        } else if is_jflap(path) {
+            self.jflap_content()
+        } else {
+            Ok(self.code.to_string())
+        }
+    }
+
+    /// Open the content read from a file, as a project for the project files, as a JFLAP machine for the JFLAP files,
+    /// as code otherwise
+    pub fn open_file_content(&mut self, path: &Path, content: String) {
+        if is_project(path) {
+            let loaded = serde_json::from_str::<Project>(&content)
+                .map_err(|error| error.to_string())
+                .and_then(|project| self.load_project(project));
+            if let Err(error) = loaded {
+                self.diagnostic = Some(Diagnostic::new(format!("Invalid project file: {}", error)));
+                return;
+            }
+        } else if is_jflap(path) {
+            if let Err(error) = self.open_jflap(&content) {
+                self.diagnostic = Some(Diagnostic::new(format!("Invalid JFLAP file: {}", error)));
+                return;
+            }
+        } else {
+            // the compile error is already reported on the code
+            if self.load_code(content).is_err() {
+                return;
+            }
+            // a code file has no layout, the states are placed by the layout chosen
+            self.relayout();
+        }
+
+        self.file_path = Some(path.to_owned());
+        self.is_dirty = false;
+        self.reset_undo();
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{TuringApp, ui::constant::Constant};
+
+use super::machine;
+
+/// Track the edits made in the code and the graph since the last synchronisation.
+/// The time of the last edit is kept to wait for the user to stop typing before synchronising.
+#[derive(Default)]
+pub struct SyncState {
+    pub code_edited: Option<f64>,
+    pub graph_edited: Option<f64>,
+    pub conflict: Option<String>,
+}
+
+impl TuringApp {
+    /// Signal that the code has been edited
+    pub fn code_changed(&mut self, time: f64) {
+        self.sync.code_edited = Some(time);
+        self.is_dirty = true;
+    }
+
+    /// Signal that the graph has been edited
+    pub fn graph_changed(&mut self, time: f64) {
+        self.sync.graph_edited = Some(time);
+    }
+
+    /// Propagate the edits to the other panel once the user stopped editing for a moment.
+    /// If both panels have been edited, nothing is propagated and the conflict is reported.
+    /// Return the delay before the next synchronisation if one is pending.
+    pub fn synchronize(&mut self, time: f64) -> Option<f64> {
+        match (self.sync.code_edited, self.sync.graph_edited) {
+            (Some(_), Some(_)) => {
+                self.sync.conflict = Some(
+                    "The code and the graph have both been edited, choose which one to keep".to_string(),
+                );
+                None
+            }
+            (Some(edited), None) | (None, Some(edited)) if time - edited < Constant::SYNC_DELAY => {
+                Some(edited + Constant::SYNC_DELAY - time)
+            }
+            (Some(_), None) => {
+                self.sync_from_code();
+                None
+            }
+            (None, Some(_)) => {
+                self.sync_from_graph();
+                None
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// Compile the code and rebuild the graph from it, the pending edits of the graph are discarded.
+    /// Return false if the code can't be compiled.
+    pub fn sync_from_code(&mut self) -> bool {
+        self.sync = SyncState::default();
+        let is_compiled = self.compile();
+        self.record_edit();
+        is_compiled
+    }
+
+    /// Apply the edits of the graph to the machine and regenerate the code,
+    /// the pending edits of the code are discarded.
+    pub fn sync_from_graph(&mut self) {
+        self.sync = SyncState::default();
+
+        let turing_machine = &mut self.turing.turing_machine;
+        for state in self.states.values() {
+            turing_machine.states[state.id as usize].name = state.name.to_string();
+        }
+        machine::reindex_names(turing_machine);
+
+        self.graph_to_code();
+        self.update();
+        self.record_edit();
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+use egui::Pos2;
+use itertools::Itertools;
+
+use crate::{TuringApp, ui::constant::Constant};
+
+use super::{layout::centroid, machine};
+
+/// Export of the graph as a picture of the TikZ `automata` library, to be included in LaTeX documents
+impl TuringApp {
+    /// The graph as a `tikzpicture`, the states are placed at their position in the graph
+    /// with `Constant::TIKZ_UNIT` pixels per centimeter
+    pub fn to_tikz(&self) -> String {
+        let turing_machine = &self.turing.turing_machine;
+        let center = centroid(&self.states.values().map(|state| state.position).collect::<Vec<Pos2>>());
+        let mut tikz = String::new();
+
+        let _ = writeln!(tikz, "% \\usetikzlibrary{{automata, arrows.meta}}");
+        let _ = writeln!(tikz, "\\begin{{tikzpicture}}[->, >={{Stealth[round]}}, auto, semithick]");
+
+        for state in self.states.values().sorted_by_key(|state| state.id) {
+            let mut options = vec!["state"];
+            if machine::is_initial(state.id) {
+                options.push("initial");
+            }
+            if turing_machine.states[state.id as usize].is_final {
+                options.push("accepting");
+            }
+
+            let _ = writeln!(
+                tikz,
+                "  \\node[{}] (q{}) at ({:.2}, {:.2}) {{{}}};",
+                options.join(", "),
+                state.id,
+                state.position.x / Constant::TIKZ_UNIT,
+                -state.position.y / Constant::TIKZ_UNIT,
+                escape(&state.name),
+            );
+        }
+
+        let edges = machine::edges(turing_machine);
+        if !edges.is_empty() {
+            let _ = writeln!(tikz, "  \\path");
+        }
+
+        for (from, to, transitions) in edges.iter() {
+            // the curves between two states in both directions are bent apart, as in the graph
+            let bend = if from == to {
+                self.states.get(from).map_or("loop above", |state| loop_direction(state.position, center))
+            } else if turing_machine.get_transition_index(*to, *from).is_some() {
+                "bend left"
+            } else {
+                ""
+            };
+
+            let label = transitions
+                .iter()
+                .map(|transition| format!("\\texttt{{{}}}", escape(&transition.to_string())))
+                .join(" \\\\ ");
+
+            let _ = writeln!(tikz, "    (q{}) edge[{}] node[align=center] {{{}}} (q{})", from, bend, label, to);
+        }
+
+        if !edges.is_empty() {
+            let _ = writeln!(tikz, "  ;");
+        }
+
+        let _ = writeln!(tikz, "\\end{{tikzpicture}}");
+        tikz
+    }
+}
+
+/// The side of the state a loop is drawn on, away from the center of the graph as in the graph
+fn loop_direction(position: Pos2, center: Pos2) -> &'static str {
+    let delta = position - center;
+
+    if delta.x.abs() > delta.y.abs() {
+        if delta.x > 0.0 { "loop right" } else { "loop left" }
+    } else if delta.y > 0.0 {
+        // the y axis of the graph goes down
+        "loop below"
+    } else {
+        "loop above"
+    }
+}
+
+/// Escape the characters that have a meaning in LaTeX
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' => "\\textbackslash{}".to_string(),
+            '~' => "\\textasciitilde{}".to_string(),
+            '^' => "\\textasciicircum{}".to_string(),
+            '{' | '}' | '#' | '$' | '%' | '&' | '_' => format!("\\{}", c),
+            c => c.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+
+use egui::Pos2;
+use turingrs::turing_machine::TuringMachine;
+
+use crate::{TuringApp, ui::constant::Constant};
+
+use super::{Selection, project::StateLayout, sync::SyncState};
+
+/// The document at a point of its history : the code, the machine and the layout of the graph.
+/// The machine is kept as the code may not compile.
+#[derive(Clone)]
+pub struct Snapshot {
+    code: String,
+    turing_machine: TuringMachine,
+    states: Vec<StateLayout>,
+}
+
+/// A reversible edit of the document
+pub enum Command {
+    /// States moved together, with their position before and after the move
+    Move(Vec<(u8, Pos2, Pos2)>),
+    /// An edit of the code or the machine, recorded once compiled or once the code is generated from the graph
+    Edit { before: Snapshot, after: Snapshot },
+}
+
+/// The commands that can be undone and redone.
+/// Only the last `Constant::UNDO_LIMIT` commands are kept.
+#[derive(Default)]
+pub struct UndoStack {
+    undo: VecDeque<Command>,
+    redo: Vec<Command>,
+    /// The document as of the last edit recorded, the starting point of the next edit
+    last: Option<Snapshot>,
+    /// The states being moved and their position when the move started
+    moving: Vec<(u8, Pos2)>,
+}
+
+impl UndoStack {
+    /// Add a new command, the commands undone can't be redone anymore
+    fn push(&mut self, command: Command) {
+        self.redo.clear();
+        self.undo.push_back(command);
+        if self.undo.len() > Constant::UNDO_LIMIT {
+            self.undo.pop_front();
+        }
+    }
+}
+
+impl TuringApp {
+    /// Forget the history and start a new one from the current document
+    pub fn reset_undo(&mut self) {
+        self.undo = UndoStack {
+            last: Some(self.snapshot()),
+            ..Default::default()
+        };
+    }
+
+    /// Record the edit made since the last one was recorded, nothing is recorded if the code didn't change.
+    /// As the code is generated from the graph, every edit of the machine change it.
+    pub fn record_edit(&mut self) {
+        let after = self.snapshot();
+
+        if let Some(before) = self.undo.last.replace(after.clone()) {
+            if before.code != after.code {
+                self.undo.push(Command::Edit { before, after });
+            }
+        }
+    }
+
+    /// Signal that states started to be moved
+    pub fn start_move(&mut self, ids: &[u8]) {
+        self.undo.moving = ids
+            .iter()
+            .filter_map(|id| self.states.get(id).map(|state| (*id, state.position)))
+            .collect();
+    }
+
+    /// Record the move of the states since the move started
+    pub fn end_move(&mut self) {
+        let moves: Vec<(u8, Pos2, Pos2)> = std::mem::take(&mut self.undo.moving)
+            .into_iter()
+            .filter_map(|(id, from)| self.states.get(&id).map(|state| (id, from, state.position)))
+            .filter(|(_, from, to)| from != to)
+            .collect();
+
+        if moves.is_empty() {
+            return;
+        }
+
+        for (id, _, to) in moves.iter() {
+            self.set_position(*id, *to);
+        }
+        self.undo.push(Command::Move(moves));
+    }
+
+    /// Revert the last command. The pending edits are recorded first to be reverted,
+    /// nothing is reverted while the code and the graph are in conflict.
+    pub fn undo(&mut self) {
+        if !self.flush_edits() {
+            return;
+        }
+
+        let Some(command) = self.undo.undo.pop_back() else {
+            return;
+        };
+
+        match &command {
+            Command::Move(moves) => {
+                for (id, from, _) in moves.iter() {
+                    self.set_position(*id, *from);
+                }
+            }
+            Command::Edit { before, .. } => self.restore(before),
+        }
+
+        self.undo.redo.push(command);
+    }
+
+    /// Apply again the last command reverted, nothing is applied while the code and the graph are in conflict
+    pub fn redo(&mut self) {
+        if !self.flush_edits() {
+            return;
+        }
+
+        let Some(command) = self.undo.redo.pop() else {
+            return;
+        };
+
+        match &command {
+            Command::Move(moves) => {
+                for (id, _, to) in moves.iter() {
+                    self.set_position(*id, *to);
+                }
+            }
+            Command::Edit { after, .. } => self.restore(after),
+        }
+
+        self.undo.undo.push_back(command);
+    }
+
+    /// Synchronise the edits not yet propagated, recording them.
+    /// Return false if both the code and the graph have been edited, as the user must choose which one to keep.
+    fn flush_edits(&mut self) -> bool {
+        match (self.sync.code_edited, self.sync.graph_edited) {
+            (Some(_), Some(_)) => return false,
+            (None, Some(_)) => self.sync_from_graph(),
+            (Some(_), None) => {
+                self.sync_from_code();
+            }
+            (None, None) => {}
+        }
+        true
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            code: self.code.to_string(),
+            turing_machine: self.turing.turing_machine.clone(),
+            states: self.layouts(),
+        }
+    }
+
+    /// Replace the document by the snapshot and restart the execution
+    fn restore(&mut self, snapshot: &Snapshot) {
+        self.code = snapshot.code.to_string();
+        self.diagnostic = None;
+        self.execute(snapshot.turing_machine.clone());
+        self.code_to_graph();
+        self.apply_layout(&snapshot.states);
+
+        self.selection = Selection::default();
+        self.sync = SyncState::default();
+        self.is_dirty = true;
+        self.undo.last = Some(snapshot.clone());
+    }
+
+    /// Move a state, the position is also kept for the next edit recorded
+    fn set_position(&mut self, id: u8, position: Pos2) {
+        let Some(state) = self.states.get_mut(&id) else {
+            return;
+        };
+        state.position = position;
+
+        let layout = self.undo.last
+            .as_mut()
+            .and_then(|last| last.states.iter_mut().find(|layout| layout.name == state.name));
+        if let Some(layout) = layout {
+            layout.position = position;
+        }
+    }
+}
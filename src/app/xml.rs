@@ -0,0 +1,7 @@
+/// Escape the characters that have a meaning in XML, for the texts and the attribute values
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,5 +1,5 @@
 use constant::Constant;
-use egui::{CentralPanel, CornerRadius, Frame, Id, Margin, SidePanel, Stroke, TopBottomPanel};
+use egui::{CentralPanel, CornerRadius, Frame, Id, Key, KeyboardShortcut, Margin, Modifiers, SidePanel, Stroke, TopBottomPanel};
 
 use crate::TuringApp;
 
@@ -9,10 +9,13 @@ mod code;
 mod control;
 mod utils;
 mod component;
-mod graph;
+pub mod graph;
 pub mod constant;
 pub mod turing;
+pub mod transition_editor;
 
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
 
 pub fn show(app: &mut TuringApp, ctx: &egui::Context) {
     
@@ -78,4 +81,23 @@ pub fn show(app: &mut TuringApp, ctx: &egui::Context) {
         });
 
     });
+
+    // Window editing the selected transition, above the panels
+    transition_editor::show(app, ctx);
+
+    shortcuts(app, ctx);
+}
+
+/// Undo and redo the edits, unless a text is edited as it has its own history
+fn shortcuts(app: &mut TuringApp, ctx: &egui::Context) {
+    if ctx.memory(|memory| memory.focused().is_some()) {
+        return;
+    }
+
+    // the redo shortcut is checked first as the undo one match it too
+    if ctx.input_mut(|input| input.consume_shortcut(&REDO_SHORTCUT)) {
+        app.redo();
+    } else if ctx.input_mut(|input| input.consume_shortcut(&UNDO_SHORTCUT)) {
+        app.undo();
+    }
 }
\ No newline at end of file
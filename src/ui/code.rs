@@ -1,13 +1,24 @@
 use egui::{
-    Align, Color32, CornerRadius, Frame, Label, Layout, Margin, Pos2, Response, RichText,
-    ScrollArea, Separator, Shape, Stroke, TextEdit, Ui, Visuals, text::LayoutJob, vec2,
+    Align, Color32, CornerRadius, Frame, Id, Key, KeyboardShortcut, Label, Layout, Margin,
+    Modifiers, Pos2, Response, RichText,
+    ScrollArea, Separator, Shape, Stroke, TextEdit, TextFormat, TopBottomPanel, Ui, Visuals,
+    text::LayoutJob, vec2,
 };
+use std::path::PathBuf;
+
 use egui_flex::{Flex, FlexDirection, item};
+use itertools::Itertools;
 
-use crate::TuringApp;
+use crate::{TuringApp, app::{Diagnostic, jflap::JFLAP_EXTENSION, project::PROJECT_EXTENSION}};
 
 use super::{component::button, constant::Constant};
 
+const SAVE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::S);
+
+use syntax::tokenize;
+
+mod syntax;
+
 pub fn show(app: &mut TuringApp, ui: &mut Ui) {
     // The button to load and compile the app
     
@@ -16,6 +27,8 @@ pub fn show(app: &mut TuringApp, ui: &mut Ui) {
                     ..Default::default()
                 });
 
+                let save_shortcut = ui.input_mut(|input| input.consume_shortcut(&SAVE_SHORTCUT));
+
                 Flex::new()
                     .direction(FlexDirection::Horizontal)
                     .wrap(true)
@@ -24,19 +37,29 @@ pub fn show(app: &mut TuringApp, ui: &mut Ui) {
                         let compile = button(flex.style_mut(), "Compile");
                         let load_file_button = button(flex.style_mut(), "Load file");
                         let load_graph_button = button(flex.style_mut(), "Load Graph");
+                        let save_button = button(flex.style_mut(), "Save");
+                        let save_as_button = button(flex.style_mut(), "Save As");
                         if flex.add(item(), compile).clicked() {
-                            app.compile();
+                            app.sync_from_code();
                         }
 
                         if flex.add(item(), load_graph_button).clicked() {
-                            app.graph_to_code();
+                            app.sync_from_graph();
                         }
 
                         let res = flex.add(item(), load_file_button);
                         load_file(app, res);
+
+                        let save = flex.add(item(), save_button).clicked() || save_shortcut;
+                        let save_as = flex.add(item(), save_as_button).clicked();
+                        save_file(app, save, save_as);
                     });
             });
 
+    conflict(app, ui);
+
+    diagnostics(app, ui);
+
     ScrollArea::vertical().show(ui, |ui| {
         Frame::new()
         .fill(Constant::BACKGROUND_2)
@@ -50,8 +73,17 @@ pub fn show(app: &mut TuringApp, ui: &mut Ui) {
                         ..Default::default()
                     })
                     .show(ui, |ui| {
-                        let mut lines_number: String = "".to_string();
+                        let mut lines_number = LayoutJob::default();
                         let number_width = Constant::get_width(ui, &Constant::code_font())-5.0;
+                        let error_line = app.diagnostic.as_ref().and_then(|d| d.line);
+
+                        // the number of the line with an error is highlighted
+                        let number_format = |line: usize| TextFormat {
+                            font_id: Constant::small_font(),
+                            color: if error_line == Some(line) {Constant::NEGATIVE_COLOR} else {Constant::PRIMARY_COLOR},
+                            line_height: Some(Constant::get_heigt(ui, &Constant::code_font())),
+                            ..Default::default()
+                        };
 
                         let mut max_width = 0.0;
                         for (i, s) in app.code.lines().enumerate() {
@@ -65,26 +97,27 @@ pub fn show(app: &mut TuringApp, ui: &mut Ui) {
                                 x.rows.iter().count()
                             });
 
-                            lines_number +=
-                                &((i + 1).to_string() + &String::from("\n".repeat(row_per_line)));
+                            lines_number.append(
+                                &((i + 1).to_string() + &String::from("\n".repeat(row_per_line))),
+                                0.0,
+                                number_format(i + 1),
+                            );
 
                             if (i + 1).to_string().len() as f32 * number_width > max_width {
                                 max_width = (i + 1).to_string().len() as f32 * number_width
                             }
                         }
 
-                        lines_number +=
-                                &((app.code.lines().count() + 1).to_string() + &String::from("\n"));
+                        lines_number.append(
+                            &((app.code.lines().count() + 1).to_string() + &String::from("\n")),
+                            0.0,
+                            number_format(app.code.lines().count() + 1),
+                        );
                         if (app.code.lines().count() + 1).to_string().len() as f32 * number_width > max_width {
                             max_width = (app.code.lines().count() + 1).to_string().len() as f32 * number_width
                         }
 
-                        let numbers = Label::new(
-                            RichText::new(lines_number)
-                                .color(Constant::PRIMARY_COLOR)
-                                .line_height(Some(Constant::get_heigt(ui, &Constant::code_font())))
-                                .font(Constant::small_font()),
-                        )
+                        let numbers = Label::new(lines_number)
                         .halign(Align::RIGHT)
                         .extend();
 
@@ -115,22 +148,130 @@ pub fn show(app: &mut TuringApp, ui: &mut Ui) {
                     ui.visuals_mut().selection.stroke = Stroke::NONE;
                     ui.visuals_mut().widgets.hovered.bg_stroke = Stroke::NONE;
 
+                    // underline the part of the code pointed by the diagnostic
+                    let diagnostic = &app.diagnostic;
+                    let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
+                        let mut job = layout_code(text, diagnostic.as_ref());
+                        job.wrap.max_width = wrap_width;
+                        ui.fonts(|f| f.layout_job(job))
+                    };
+
                     let code_edit = TextEdit::multiline(&mut app.code)
                         .background_color(Color32::TRANSPARENT)
                         .code_editor()
                         .text_color(Color32::WHITE)
-                        .font(Constant::code_font());
+                        .font(Constant::code_font())
+                        .layouter(&mut layouter);
 
-                    ui.add_sized(
+                    if ui.add_sized(
                         ui.available_size(),
                         code_edit,
-                    );
+                    ).changed() {
+                        app.code_changed(ui.input(|input| input.time));
+                    }
                 });
             });
         })
     });
 }
 
+/// Display the conflict between the code and the graph, with the choice of the one to keep
+fn conflict(app: &mut TuringApp, ui: &mut Ui) {
+    let Some(conflict) = app.sync.conflict.to_owned() else {
+        return;
+    };
+
+    TopBottomPanel::bottom(Id::new("Conflict"))
+        .frame(Frame {
+            fill: Constant::BACKGROUND_2,
+            inner_margin: Margin::same(5),
+            ..Default::default()
+        })
+        .show_inside(ui, |ui| {
+            ui.label(
+                RichText::new(conflict)
+                    .font(Constant::code_font())
+                    .color(Constant::NEGATIVE_COLOR),
+            );
+
+            ui.horizontal(|ui| {
+                let keep_code = button(ui.style_mut(), "Keep code");
+                if ui.add(keep_code).clicked() {
+                    app.sync_from_code();
+                }
+
+                let keep_graph = button(ui.style_mut(), "Keep graph");
+                if ui.add(keep_graph).clicked() {
+                    app.sync_from_graph();
+                }
+            });
+        });
+}
+
+/// Display the error of the last compilation under the code
+fn diagnostics(app: &mut TuringApp, ui: &mut Ui) {
+    let Some(diagnostic) = &app.diagnostic else {
+        return;
+    };
+
+    TopBottomPanel::bottom(Id::new("Diagnostics"))
+        .frame(Frame {
+            fill: Constant::BACKGROUND_2,
+            inner_margin: Margin::same(5),
+            ..Default::default()
+        })
+        .show_inside(ui, |ui| {
+            if let Some((line, column)) = diagnostic.line.zip(diagnostic.column) {
+                ui.label(
+                    RichText::new(format!("Error at line {}, column {}", line, column))
+                        .font(Constant::code_font())
+                        .color(Constant::NEGATIVE_COLOR),
+                );
+            }
+
+            ScrollArea::both()
+                .max_height(Constant::DIAGNOSTIC_HEIGHT)
+                .show(ui, |ui| {
+                    ui.add(
+                        Label::new(
+                            RichText::new(&diagnostic.message)
+                                .font(Constant::code_font())
+                                .color(Constant::PRIMARY_COLOR),
+                        )
+                        .extend(),
+                    );
+                });
+        });
+}
+
+/// Layout the code colored by token, with the span pointed by the diagnostic underlined
+fn layout_code(code: &str, diagnostic: Option<&Diagnostic>) -> LayoutJob {
+    let error_span = diagnostic.and_then(|d| d.span(code)).unwrap_or(0..0);
+
+    let mut job = LayoutJob::default();
+    for (token, range) in tokenize(code) {
+        let format = TextFormat::simple(Constant::code_font(), token.color());
+
+        // split the token on the bounds of the error span
+        let mut bounds = vec![range.start, range.end];
+        for bound in [error_span.start, error_span.end] {
+            if range.start < bound && bound < range.end {
+                bounds.push(bound);
+            }
+        }
+        bounds.sort();
+
+        for (start, end) in bounds.into_iter().tuple_windows() {
+            let mut format = format.clone();
+            if error_span.start <= start && end <= error_span.end && start < end {
+                format.underline = Stroke::new(2.0, Constant::NEGATIVE_COLOR);
+            }
+            job.append(&code[start..end], 0.0, format);
+        }
+    }
+    job
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 fn load_file(app: &mut TuringApp, res: Response) {
     use std::fs;
@@ -144,14 +285,23 @@ fn load_file(app: &mut TuringApp, res: Response) {
         // }
 
         app.promise = Some(Promise::spawn_thread("load_file", || {
-            FileDialog::new().add_filter("ext", &["tm"]).pick_file()
+            FileDialog::new().add_filter("ext", &["tm", PROJECT_EXTENSION, JFLAP_EXTENSION]).pick_file()
         }));
     }
 
     if let Some(promise) = &app.promise {
         if let Some(path) = (promise).ready() {
-            if let Some(path) = path {
-                app.code = fs::read_to_string(path).expect("cannot read file");
+            if let Some(path) = path.to_owned() {
+                match fs::read_to_string(&path) {
+                    Ok(content) => app.open_file_content(&path, content),
+                    Err(error) => {
+                        app.diagnostic = Some(Diagnostic::without_position(format!(
+                            "Cannot read the file {}: {}",
+                            path.display(),
+                            error
+                        )))
+                    }
+                }
                 app.promise = None;
             }
         } else {
@@ -159,23 +309,141 @@ fn load_file(app: &mut TuringApp, res: Response) {
     }
 }
 
+/// Write the code to the current file, or ask for a file if there is none or if `save_as` is true
+#[cfg(not(target_arch = "wasm32"))]
+fn save_file(app: &mut TuringApp, save: bool, save_as: bool) {
+    use std::fs;
+
+    use poll_promise::Promise;
+    use rfd::FileDialog;
+
+    match &app.file_path {
+        Some(path) if save && !save_as => write_file(app, path.to_owned()),
+        _ if save || save_as => {
+            let file_name = app.file_name();
+            app.save_promise = Some(Promise::spawn_thread("save_file", || {
+                FileDialog::new()
+                    .add_filter("ext", &["tm"])
+                    .add_filter("project", &[PROJECT_EXTENSION])
+                    .add_filter("jflap", &[JFLAP_EXTENSION])
+                    .set_file_name(file_name)
+                    .save_file()
+                    .map(Ok)
+            }));
+        }
+        _ => {}
+    }
+
+    if let Some(promise) = &app.save_promise {
+        if let Some(path) = promise.ready() {
+            if let Some(Ok(path)) = path.to_owned() {
+                write_file(app, path);
+            }
+            app.save_promise = None;
+        }
+    }
+
+    fn write_file(app: &mut TuringApp, path: PathBuf) {
+        let written = app
+            .file_content(&path)
+            .and_then(|content| fs::write(&path, content).map_err(|error| error.to_string()));
+        match written {
+            Ok(()) => {
+                app.file_path = Some(path);
+                app.is_dirty = false;
+            }
+            Err(error) => {
+                app.diagnostic = Some(Diagnostic::without_position(format!(
+                    "Cannot write the file {}: {}",
+                    path.display(),
+                    error
+                )))
+            }
+        }
+    }
+}
+
+/// Download the code, the browser ask where to save it
+#[cfg(target_arch = "wasm32")]
+fn save_file(app: &mut TuringApp, save: bool, save_as: bool) {
+    use std::path::Path;
+
+    use poll_promise::Promise;
+    use rfd::AsyncFileDialog;
+
+    use crate::app::{jflap::is_jflap, project::is_project};
+
+    if save || save_as {
+        let file_name = app.file_name();
+        // the content depends on the extension of the file picked, so every format is ready before asking
+        let code = app.file_content(Path::new("untitled.tm"));
+        let project = app.file_content(Path::new(&format!("untitled.{}", PROJECT_EXTENSION)));
+        let jflap = app.file_content(Path::new(&format!("untitled.{}", JFLAP_EXTENSION)));
+        app.save_promise = Some(Promise::spawn_local(async move {
+            let file = AsyncFileDialog::new()
+                .add_filter("ext", &["tm"])
+                .add_filter("project", &[PROJECT_EXTENSION])
+                .add_filter("jflap", &[JFLAP_EXTENSION])
+                .set_file_name(file_name)
+                .save_file()
+                .await?;
+            let path = PathBuf::from(file.file_name());
+            let content = if is_project(&path) {
+                project
+            } else if is_jflap(&path) {
+                jflap
+            } else {
+                code
+            };
+            let written = match content {
+                Ok(content) => file.write(content.as_bytes()).await.map_err(|error| error.to_string()),
+                Err(error) => Err(error),
+            };
+            Some(written
+                .map(|_| path.to_owned())
+                .map_err(|error| format!("Cannot write the file {}: {}", path.display(), error)))
+        }));
+    }
+
+    if let Some(promise) = &app.save_promise {
+        if let Some(path) = promise.ready() {
+            match path.to_owned() {
+                Some(Ok(path)) => {
+                    app.file_path = Some(path);
+                    app.is_dirty = false;
+                }
+                Some(Err(error)) => app.diagnostic = Some(Diagnostic::without_position(error)),
+                None => {}
+            }
+            app.save_promise = None;
+        }
+    }
+}
+
+/// Upload a file, the browser ask which file to open
 #[cfg(target_arch = "wasm32")]
 fn load_file(app: &mut TuringApp, res: Response) {
-    use std::path::PathBuf;
+    use poll_promise::Promise;
+    use rfd::AsyncFileDialog;
 
     if res.clicked() {
         app.promise_wasm = Some(Promise::spawn_local(async move {
-            AsyncFileDialog::new()
-                .add_filter("ext", &["tm"])
+            let file = AsyncFileDialog::new()
+                .add_filter("ext", &["tm", PROJECT_EXTENSION, JFLAP_EXTENSION])
                 .pick_file()
-                .await
+                .await?;
+            let content = String::from_utf8(file.read().await)
+                .inspect_err(|error| log::error!("cannot read file {}: {}", file.file_name(), error))
+                .ok()?;
+            Some((PathBuf::from(file.file_name()), content))
         }));
     }
 
-    if let Some(promise) = &app.promise {
-        if let Some(path) = (promise).ready() {
-            app.code = fs::read_to_string(path.as_ref().unwrap()).expect("cannot read file");
-        } else {
+    if app.promise_wasm.as_ref().is_some_and(|promise| promise.ready().is_some()) {
+        let file = app.promise_wasm.take().and_then(|promise| promise.block_and_take());
+
+        if let Some((path, content)) = file {
+            app.open_file_content(&path, content);
         }
     }
 }
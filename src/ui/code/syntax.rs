@@ -0,0 +1,169 @@
+use std::ops::Range;
+
+use egui::Color32;
+
+use crate::ui::constant::Constant;
+
+/// The kinds of token of the turing machine language
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Token {
+    /// A state name : `q_name`
+    State,
+    /// A character read or written on a ribbon
+    Symbol,
+    /// The blank character `ç`
+    Blank,
+    /// The move of a head : `L`, `R` or `N`
+    Direction,
+    /// The `->` separating the read characters from the writes
+    Arrow,
+    /// The `{` and `}` around the rules
+    Bracket,
+    /// The `|`, `,` and `;` separators
+    Separator,
+    /// A comment until the end of the line
+    Comment,
+    /// Anything else, including whitespaces
+    Text,
+}
+
+impl Token {
+    /// The color used to display the token in the code editor
+    pub fn color(self) -> Color32 {
+        match self {
+            Token::State => Constant::CODE_STATE,
+            Token::Symbol => Constant::CODE_SYMBOL,
+            Token::Blank => Constant::CODE_BLANK,
+            Token::Direction => Constant::CODE_DIRECTION,
+            Token::Arrow => Constant::CODE_ARROW,
+            Token::Bracket => Constant::CODE_BRACKET,
+            Token::Separator => Constant::CODE_SEPARATOR,
+            Token::Comment => Constant::CODE_COMMENT,
+            Token::Text => Constant::CODE_TEXT,
+        }
+    }
+}
+
+/// Split the code into tokens with their byte range, every byte of the code belong to a token.
+/// The tokenizer never fails so the code can be highlighted while being typed.
+pub fn tokenize(code: &str) -> Vec<(Token, Range<usize>)> {
+    let mut tokens: Vec<(Token, Range<usize>)> = vec![];
+
+    // true between the brackets of a rule
+    let mut in_rule = false;
+    // true after the arrow of a transition
+    let mut after_arrow = false;
+    // index of the element between the commas after the arrow,
+    // the even ones are the moves and the odd ones the written characters
+    let mut slot = 0;
+
+    let mut chars = code.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+
+        let token = match c {
+            '/' if code[end..].starts_with('/') => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+                end = chars.peek().map(|(i, _)| *i).unwrap_or(code.len());
+                Token::Comment
+            }
+            '{' => {
+                (in_rule, after_arrow, slot) = (true, false, 0);
+                Token::Bracket
+            }
+            '}' => {
+                in_rule = false;
+                Token::Bracket
+            }
+            '|' if in_rule => {
+                (after_arrow, slot) = (false, 0);
+                Token::Separator
+            }
+            ',' if in_rule => {
+                if after_arrow {
+                    slot += 1;
+                }
+                Token::Separator
+            }
+            ';' => Token::Separator,
+            '-' if in_rule && code[end..].starts_with('>') => {
+                chars.next();
+                end += 1;
+                (after_arrow, slot) = (true, 0);
+                Token::Arrow
+            }
+            c if c.is_whitespace() => Token::Text,
+            'ç' if in_rule => Token::Blank,
+            _ if in_rule => {
+                if after_arrow && slot % 2 == 0 {
+                    Token::Direction
+                } else {
+                    Token::Symbol
+                }
+            }
+            'q' if code[end..].starts_with('_') => {
+                while chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_').is_some() {}
+                end = chars.peek().map(|(i, _)| *i).unwrap_or(code.len());
+                Token::State
+            }
+            _ => Token::Text,
+        };
+
+        // merge the token with the previous one if they are of the same kind
+        match tokens.last_mut() {
+            Some((last, range)) if *last == token => range.end = end,
+            _ => tokens.push((token, start..end)),
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Token, tokenize};
+
+    /// The tokens with their text, the whitespaces left out
+    fn tokens(code: &str) -> Vec<(Token, &str)> {
+        tokenize(code)
+            .into_iter()
+            .map(|(token, range)| (token, &code[range]))
+            .filter(|(token, text)| *token != Token::Text || !text.trim().is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn rule_is_tokenized() {
+        assert_eq!(tokens("q_a {0, ç -> R, 1, N} q_b;"), vec![
+            (Token::State, "q_a"),
+            (Token::Bracket, "{"),
+            (Token::Symbol, "0"),
+            (Token::Separator, ","),
+            (Token::Blank, "ç"),
+            (Token::Arrow, "->"),
+            (Token::Direction, "R"),
+            (Token::Separator, ","),
+            (Token::Symbol, "1"),
+            (Token::Separator, ","),
+            (Token::Direction, "N"),
+            (Token::Bracket, "}"),
+            (Token::State, "q_b"),
+            (Token::Separator, ";"),
+        ]);
+    }
+
+    #[test]
+    fn comment_runs_to_the_end_of_the_line() {
+        assert_eq!(tokens("// initial: q_a {\nq_a"), vec![(Token::Comment, "// initial: q_a {"), (Token::State, "q_a")]);
+    }
+
+    #[test]
+    fn every_byte_belongs_to_a_token() {
+        let code = "q_a {0, ç -> R\n | ç, ç -> L} q_a; // ç\n{ -";
+        let ranges: Vec<_> = tokenize(code).into_iter().map(|(_, range)| range).collect();
+
+        assert_eq!(ranges.first().unwrap().start, 0);
+        assert_eq!(ranges.last().unwrap().end, code.len());
+        assert!(ranges.windows(2).all(|pair| pair[0].end == pair[1].start));
+    }
+}
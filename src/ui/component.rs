@@ -1,4 +1,4 @@
-use egui::{vec2, Button, Color32, Image, ImageSource, Label, Margin, RichText, Stroke, Style, TextEdit, Ui};
+use egui::{pos2, vec2, Button, Color32, Image, ImageSource, Label, Margin, Rect, RichText, Stroke, Style, TextEdit, Ui};
 
 use crate::TuringApp;
 
@@ -30,6 +30,21 @@ pub fn button_image<'a>(style: &mut Style, source: ImageSource<'a>) -> Button<'a
         .corner_radius(10.0)
 }
 
+/// A basic button with an image mirrored horizontally
+pub fn button_image_mirrored<'a>(style: &mut Style, source: ImageSource<'a>) -> Button<'a> {
+
+    style.spacing.button_padding = vec2(10.0, 5.0);
+
+    Button::image(
+        Image::new(source)
+            .fit_to_exact_size((24.0, 24.0).into())
+            .uv(Rect::from_min_max(pos2(1.0, 0.0), pos2(0.0, 1.0)))
+    )
+        .stroke(Stroke::new(1.0, Constant::BORDER))
+        .fill(Constant::BACKGROUND)
+        .corner_radius(10.0)
+}
+
 /// A basic label
 pub fn label(style: &mut Style, text: &str) -> Label {
     Label::new(
@@ -77,9 +77,58 @@ impl Constant {
     pub const MAX_FORCE: f32 = 100.0;
     pub const STATE_RADIUS: f32 = 40.0;
     pub const TRANSITION_THICKNESS: f32 = 1.0;
-    pub const STABILITY_TRESHOLD: f32 = 0.01;
+    pub const STABILITY_TRESHOLD: f32 = 0.05;
+    pub const DAMPING: f32 = 0.5;
+    pub const COOLING: f32 = 0.98;
+    pub const MIN_TEMPERATURE: f32 = 0.01;
+    pub const LAYOUT_SPACING: f32 = 200.0;
+    pub const LAYOUT_SWEEPS: usize = 4;
+    pub const LAYOUT_ANIMATION_SPEED: f32 = 6.0;
+    pub const LAYOUT_SNAP_DISTANCE: f32 = 0.5;
     pub const ARROW_SIZE: f32 = 20.0;
     pub const TRANSITION_CURVATURE: f32 = 20.0;
+    pub const HANDLE_RADIUS: f32 = 4.0;
+    pub const ROUTING_MARGIN: f32 = 10.0;
+    pub const ROUTING_STEP: f32 = 10.0;
+    pub const ROUTING_ITERATIONS: usize = 30;
+    pub const ROUTING_SAMPLES: usize = 20;
+    pub const LABEL_PLACEMENT_ITERATIONS: usize = 20;
+    pub const ACCEPTING_RING_GAP: f32 = 6.0;
+    pub const INITIAL_ARROW_LENGTH: f32 = 40.0;
+    pub const COLOR_SWATCH_SIZE: f32 = 20.0;
+    pub const EXPORT_MARGIN: f32 = 20.0;
+    pub const EXPORT_PNG_SCALE: f32 = 2.0;
+    pub const TIKZ_UNIT: f32 = 80.0;
+    pub const DOT_POINTS_PER_INCH: f32 = 72.0;
+    pub const STATE_COLORS: [Color32; 6] = [
+        Color32::WHITE,
+        Color32::from_rgb(86, 156, 214),
+        Color32::from_rgb(106, 190, 106),
+        Color32::from_rgb(230, 180, 80),
+        Color32::from_rgb(220, 100, 100),
+        Color32::from_rgb(180, 120, 220),
+    ];
+
+    // Execution
+    pub const DEFAULT_SPEED: f32 = 5.0;
+    pub const MIN_SPEED: f32 = 0.5;
+    pub const MAX_SPEED: f32 = 100.0;
+    pub const MAX_STEPS_PER_FRAME: usize = 1000;
+    pub const HISTORY_LIMIT: usize = 10_000;
+
+    // Code
+    pub const DIAGNOSTIC_HEIGHT: f32 = 100.0;
+    pub const SYNC_DELAY: f64 = 0.8;
+    pub const UNDO_LIMIT: usize = 100;
+    pub const CODE_TEXT: Color32 = Color32::WHITE;
+    pub const CODE_STATE: Color32 = Color32::from_rgb(86, 156, 214);
+    pub const CODE_SYMBOL: Color32 = Color32::from_rgb(206, 145, 120);
+    pub const CODE_BLANK: Color32 = Color32::from_rgb(197, 134, 192);
+    pub const CODE_DIRECTION: Color32 = Color32::from_rgb(220, 220, 170);
+    pub const CODE_ARROW: Color32 = Color32::from_rgb(181, 206, 168);
+    pub const CODE_BRACKET: Color32 = Color32::from_rgb(255, 215, 0);
+    pub const CODE_SEPARATOR: Color32 = Color32::from_rgb(180, 180, 180);
+    pub const CODE_COMMENT: Color32 = Constant::FOREGROUND;
 
     // Ribbon
     pub const SQUARE_SIZE: f32 = 30.0;
@@ -1,24 +1,31 @@
-use egui::{Frame, Margin, Ui};
+use std::time::Duration;
+
+use egui::{Checkbox, DragValue, Frame, Margin, Slider, Ui};
 use egui_flex::{Flex, FlexAlign, FlexJustify, item};
 use turingrs::turing_machine::TuringExecutor;
 
 use crate::TuringApp;
 
 use super::{
-    component::{button, button_image, label, label_colored, text_edit_single},
+    component::{button, button_image, button_image_mirrored, label, label_colored, text_edit_single},
     constant::Constant,
 };
 
 /// This module display the controls of the application, including the following :
 /// - The word input and button to update it
-/// - The play/pause/next/reset buttons
+/// - The play/pause/next/reset buttons and the execution speed
 /// - The steps counter and result (Accepted/Rejected)
+/// - The timeline of the steps already computed
 pub fn show(app: &mut TuringApp, ui: &mut Ui) {
     ui.columns_const(|[left, center, right]| {
         input(app, left);
         control_buttons(app, center);
         result(app, right);
     });
+
+    timeline(app, ui);
+
+    run(app, ui);
 }
 
 /// The input part manage the input passed to the turing machine
@@ -47,6 +54,7 @@ fn input(app: &mut TuringApp, ui: &mut Ui) {
 /// The controls part manage the execution of the turing machine.
 /// - The play button start to compute the steps at regular interval until the final step.
 /// - The pause button stop the automatic computation of steps.
+/// - The previous button go back to the previous step.
 /// - The next button compute the next step.
 /// - The reset button reset the turing machine execution to the initial state.
 fn control_buttons(app: &mut TuringApp, ui: &mut Ui) {
@@ -64,6 +72,10 @@ fn control_buttons(app: &mut TuringApp, ui: &mut Ui) {
                 flex.style_mut(),
                 egui::include_image!("../../assets/pause.png"),
             );
+            let previous_button = button_image_mirrored(
+                flex.style_mut(),
+                egui::include_image!("../../assets/next.png"),
+            );
             let next_next = button_image(
                 flex.style_mut(),
                 egui::include_image!("../../assets/next.png"),
@@ -80,12 +92,25 @@ fn control_buttons(app: &mut TuringApp, ui: &mut Ui) {
             if flex.add(item(), pause_button).clicked() {
                 pause(app);
             }
+            if flex.add(item(), previous_button).clicked() {
+                previous(app);
+            }
             if flex.add(item(), next_next).clicked() {
                 next(app);
             };
             if flex.add(item(), reset_button).clicked() {
                 reset(app);
             }
+
+            // steps per second of the automatic execution
+            let speed = Slider::new(&mut app.playback.speed, Constant::MIN_SPEED..=Constant::MAX_SPEED)
+                .logarithmic(true)
+                .suffix(" steps/s");
+            flex.add(item(), speed);
+
+            // compute as many steps as possible per frame
+            let max_speed = Checkbox::new(&mut app.playback.is_max_speed, "Max");
+            flex.add(item(), max_speed);
         });
 }
 
@@ -97,10 +122,12 @@ fn result(app: &mut TuringApp, ui: &mut Ui) {
         .align_items(FlexAlign::Center)
         .justify(FlexJustify::SpaceAround)
         .show(ui, |flex| {
-            let steps = label(flex.style_mut(), &format!("Steps : {}", app.count));
+            let steps = label(flex.style_mut(), &format!("Steps : {}", app.history.cursor()));
             flex.add(item(), steps);
 
-            let result = match app.is_accepted {
+            // the result is only known on the last step
+            let is_accepted = app.is_accepted.filter(|_| app.history.is_at_end());
+            let result = match is_accepted {
                 Some(accepted) => match accepted {
                     true => label_colored(flex.style_mut(), "Accepted", Constant::POSITIVE_COLOR),
                     false => label_colored(flex.style_mut(), "Refused", Constant::NEGATIVE_COLOR),
@@ -111,18 +138,141 @@ fn result(app: &mut TuringApp, ui: &mut Ui) {
         });
 }
 
-fn play(app: &mut TuringApp) {}
+/// The timeline display the steps still in the history, dragging the slider go back and forth in time.
+/// The field next to it jump to any step, computing the missing ones over the next frames if needed.
+fn timeline(app: &mut TuringApp, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        let mut cursor = app.history.cursor();
+        let mut target = app.playback.target.unwrap_or(cursor);
+
+        ui.spacing_mut().slider_width = (ui.available_width() - 150.0).max(0.0);
+        let slider = ui.add_enabled(
+            app.history.last() > app.history.first(),
+            Slider::new(&mut cursor, app.history.first()..=app.history.last()).show_value(false),
+        );
+        if slider.changed() {
+            pause(app);
+            app.history.go_to(cursor);
+        }
+
+        // a target further than the history can hold would evict every step kept and may never be reached
+        let reachable = if app.is_accepted.is_some() { 0 } else { Constant::HISTORY_LIMIT };
+        let go_to_label = label(ui.style_mut(), "Go to");
+        ui.add(go_to_label);
+        if ui.add(DragValue::new(&mut target).range(app.history.first()..=app.history.last() + reachable)).changed() {
+            pause(app);
+            go_to(app, target);
+        }
+    });
+}
+
+/// Start the automatic execution, the steps are computed by `run` on the next frames
+fn play(app: &mut TuringApp) {
+    if app.is_accepted.is_none() || !app.history.is_at_end() {
+        app.playback.is_running = true;
+        app.playback.last_step = None;
+    }
+}
+
+/// Stop the automatic execution, the current step and counter are kept
+fn pause(app: &mut TuringApp) {
+    app.playback.is_running = false;
+    app.playback.target = None;
+}
+
+/// Compute the steps due since the last frame when the execution is running.
+/// Instead of blocking, a repaint is scheduled for the moment the next step is due.
+fn run(app: &mut TuringApp, ui: &mut Ui) {
+    if let Some(target) = app.playback.target {
+        // the steps already computed are skipped, only the missing ones are fetched
+        app.history.go_to(app.history.last());
+        let due = target.saturating_sub(app.history.last()).min(Constant::MAX_STEPS_PER_FRAME);
+        let mut is_ended = false;
+        for _ in 0..due {
+            if !next(app) {
+                is_ended = true;
+                break;
+            }
+        }
+
+        if is_ended || app.history.last() >= target {
+            app.playback.target = None;
+            app.history.go_to(target);
+        } else {
+            ui.ctx().request_repaint();
+        }
+        return;
+    }
+
+    if !app.playback.is_running {
+        return;
+    }
+
+    if app.playback.is_max_speed {
+        for _ in 0..Constant::MAX_STEPS_PER_FRAME {
+            if !next(app) {
+                break;
+            }
+        }
+        ui.ctx().request_repaint();
+        return;
+    }
+
+    let now = ui.input(|input| input.time);
+    let interval = 1.0 / app.playback.speed as f64;
+    let last_step = *app.playback.last_step.get_or_insert(now - interval);
+
+    // catch up on the steps missed if the frames are slower than the speed
+    let due = (((now - last_step) / interval) as usize).min(Constant::MAX_STEPS_PER_FRAME);
+    for _ in 0..due {
+        if !next(app) {
+            break;
+        }
+    }
+    let last_step = last_step + due as f64 * interval;
+    app.playback.last_step = Some(last_step);
+
+    if app.playback.is_running {
+        ui.ctx().request_repaint_after(Duration::from_secs_f64((last_step + interval - now).max(0.0)));
+    }
+}
+
+/// Go back to the previous step of the history
+fn previous(app: &mut TuringApp) {
+    pause(app);
+    app.history.go_to(app.history.cursor().saturating_sub(1));
+}
+
+/// Display the step at the index, the steps not yet computed are fetched from the executor
+/// by `run` on the next frames, until the index is reached or the execution ends.
+fn go_to(app: &mut TuringApp, index: usize) {
+    if app.history.last() < index && app.is_accepted.is_none() {
+        app.playback.target = Some(index);
+    } else {
+        app.history.go_to(index);
+    }
+}
+
+/// Display the next step of the history if the displayed one is not the last.
+/// Else fetch the next state from the executor if there is one.
+/// if not check if the resulting state is rejecting or accepting, then stop the execution.
+/// Return true if a step has been displayed.
+fn next(app: &mut TuringApp) -> bool {
+    if !app.history.is_at_end() {
+        app.history.go_to(app.history.cursor() + 1);
+        return true;
+    }
 
-fn pause(app: &mut TuringApp) {}
+    if app.is_accepted.is_some() {
+        app.playback.is_running = false;
+        return false;
+    }
 
-/// Fetch the next state from the executor if there is one.
-/// if not check if the resulting state is rejecting or accepting
-fn next(app: &mut TuringApp) {
     match app.turing.as_iter().next() {
-        // Update the current step and increment the counter
+        // Record the step, the counter is the position in the history
         Some(next_step) => {
-            app.current_step = next_step;
-            app.count += 1;
+            app.history.push(next_step, app.turing.get_state_pointer(), &app.turing.turing_machine);
+            true
         }
         // If there is no next step, check if the current state is final.
         // If yes, then it's accepted, if not it's rejected
@@ -132,7 +282,9 @@ fn next(app: &mut TuringApp) {
                     .turing_machine
                     .get_state(app.turing.get_state_pointer())
                     .is_final,
-            )
+            );
+            app.playback.is_running = false;
+            false
         }
     }
 }
@@ -1,19 +1,43 @@
-use std::collections::{HashMap, hash_map::Entry};
+use std::{collections::{HashMap, hash_map::{DefaultHasher, Entry}}, hash::{Hash, Hasher}};
 
-use egui::{Button, Pos2, Rect, Scene, Ui, UiBuilder, Vec2};
+use egui::{Button, Checkbox, Color32, ComboBox, CornerRadius, Event, Frame, Key, Margin, Pos2, Rect, Scene, Sense, Stroke, StrokeKind, Ui, UiBuilder, Vec2};
 use itertools::Itertools;
-use organic::apply_force;
+use organic::{Grid, apply_force};
 use state::draw_states;
-use transition::{draw_loop_transitions, draw_normal_transitions};
+use routing::{place_labels, route_around};
+use transition::{Curve, draw_curve_transitions, labels_size, loop_curve, normal_curve};
+use turingrs::turing_machine::TuringMachine;
 
-use crate::TuringApp;
+use crate::{TuringApp, app::{Diagnostic, Selection, layout::{Alignment, LayoutKind}}};
 
-use super::turing::Transition;
+use super::{component::button, constant::Constant, transition_editor::TransitionEditor, turing::{CurveOffset, State, Transition}};
 
+mod export;
+mod import;
 mod organic;
+mod routing;
 mod state;
 mod transition;
 
+/// An edit of the graph requested while drawing it, applied once the whole graph is drawn
+pub enum GraphEdit {
+    /// The graph has been edited while drawing it
+    Edited,
+    /// Rename a state, the code is rewritten instead of being generated from the graph
+    Rename(u8, String),
+    AddState(Pos2),
+    DeleteStates(Vec<u8>),
+    DeleteTransition(u8, u8),
+    ToggleAccepting(u8),
+    SetInitial(u8),
+    /// Move states to align them, only the layout of the graph is modified
+    Align(Vec<u8>, Alignment),
+    /// Change the color of states, only the layout of the graph is modified
+    Recolor(Vec<u8>, Color32),
+    /// Paste the states copied as text
+    Paste(String),
+}
+
 pub fn show(app: &mut TuringApp, ui: &mut Ui) {
     // current rect of the element inside the scene
     let mut inner_rect = Rect::ZERO;
@@ -22,14 +46,26 @@ pub fn show(app: &mut TuringApp, ui: &mut Ui) {
     // translation and zoom reference
     let mut scene_rect = app.graph_rect;
 
-    let is_stable = apply_force(app);
+    toolbar(app, ui);
+
+    // the layout animation and the frozen graph replace the force simulation
+    let is_stable = if app.animate_layout(ui.input(|input| input.stable_dt)) {
+        false
+    } else if app.layout.is_frozen {
+        true
+    } else {
+        apply_force(app)
+    };
 
+    let mut edits: Vec<GraphEdit> = vec![];
 
     let scene_response = Scene::new()
         .show(ui, &mut scene_rect, |ui| {
-            draw_transitions(app, ui);
+            select_box(app, ui);
 
-            draw_states(app, ui);
+            draw_transitions(app, ui, &mut edits);
+
+            draw_states(app, ui, &mut edits);
 
             inner_rect = ui.min_rect();
         })
@@ -44,36 +80,249 @@ pub fn show(app: &mut TuringApp, ui: &mut Ui) {
 
     // If the graph canvas is clicked
     if scene_response.clicked() {
-        app.selection.selected_state = None;
-        app.selection.selected_transition = None;
+        app.selection = Selection::default();
+    }
+
+    // create a new state where the empty canvas is double clicked
+    if scene_response.double_clicked() {
+        if let Some(position) = scene_response.interact_pointer_pos() {
+            edits.push(GraphEdit::AddState(position));
+        }
+    }
+
+    // delete the selection with the keyboard, unless a text is being edited
+    let can_delete = !app.selection.is_editing && ui.ctx().memory(|memory| memory.focused().is_none());
+    if can_delete && ui.input(|input| input.key_pressed(Key::Delete) || input.key_pressed(Key::Backspace)) {
+        if !app.selection.selected_states.is_empty() {
+            edits.push(GraphEdit::DeleteStates(app.selection.selected_states.iter().copied().collect()));
+        } else if let Some((parent_id, id)) = app.selection.selected_transition {
+            edits.push(GraphEdit::DeleteTransition(parent_id, id));
+        }
+    }
+
+    clipboard(app, ui, &mut edits);
+
+    apply_edits(app, ui, edits);
+
+    // a transition being edited is opened in the transition editor
+    if let (Some((parent_id, id)), true) = (app.selection.selected_transition, app.selection.is_editing) {
+        let is_opened = app.transition_editor.as_ref().is_some_and(|editor| (editor.parent_id, editor.id) == (parent_id, id));
+        let transition = app.turing.turing_machine.states
+            .get(parent_id as usize)
+            .and_then(|state| state.transitions.get(id as usize));
+
+        if let (false, Some(transition)) = (is_opened, transition) {
+            app.transition_editor = Some(TransitionEditor::new(parent_id, id, transition));
+        }
     }
 
     // If the graph didn't reach a stable state in the current frame, ask to draw the next even if no user interaction
-    if !is_stable || true {
+    if !is_stable {
         ui.ctx().request_repaint();
     }
 }
 
+/// The choice of the automatic layout of the graph, above it
+fn toolbar(app: &mut TuringApp, ui: &mut Ui) {
+    Frame {
+        inner_margin: Margin::same(5),
+        ..Default::default()
+    }
+    .show(ui, |ui| {
+        ui.horizontal(|ui| {
+            ComboBox::from_id_salt("layout_kind")
+                .selected_text(app.layout.kind.name())
+                .show_ui(ui, |ui| {
+                    for kind in LayoutKind::ALL {
+                        ui.selectable_value(&mut app.layout.kind, kind, kind.name());
+                    }
+                });
+
+            let relayout_button = button(ui.style_mut(), "Re-layout");
+            if ui.add(relayout_button).clicked() {
+                app.relayout();
+            }
+
+            if ui.add(Checkbox::new(&mut app.layout.is_frozen, "Freeze")).changed() {
+                app.layout.reheat();
+            }
+
+            export::menu(app, ui);
+
+            import::positions_button(app, ui);
+        });
+    });
+}
+
+/// Apply the edits requested while drawing the graph, then signal the graph has changed if it need to be synchronised
+fn apply_edits(app: &mut TuringApp, ui: &mut Ui, edits: Vec<GraphEdit>) {
+    // a rename update the code itself and the layout is not part of the code, no synchronisation is needed.
+    // a paste changes the graph only if it succeeds
+    let mut is_graph_changed = edits.iter().any(|edit| {
+        !matches!(edit, GraphEdit::Rename(..) | GraphEdit::Align(..) | GraphEdit::Recolor(..) | GraphEdit::Paste(..))
+    });
+
+    for edit in edits {
+        match edit {
+            GraphEdit::Edited => {}
+            GraphEdit::Rename(id, name) => match app.rename_state(id, &name) {
+                Ok(()) => app.selection = Selection::default(),
+                Err(error) => app.selection.rename_error = Some(error),
+            },
+            GraphEdit::AddState(position) => app.add_state_at(position),
+            GraphEdit::DeleteStates(ids) => app.delete_states(&ids),
+            GraphEdit::DeleteTransition(parent_id, id) => app.delete_transition(parent_id, id),
+            GraphEdit::ToggleAccepting(id) => app.toggle_accepting(id),
+            GraphEdit::SetInitial(id) => app.set_initial_state(id),
+            GraphEdit::Align(ids, alignment) => app.align_states(&ids, alignment),
+            GraphEdit::Recolor(ids, color) => app.recolor_states(&ids, color),
+            GraphEdit::Paste(text) => {
+                match app.paste_states(&text, app.graph_rect.center()) {
+                    Ok(()) => is_graph_changed = true,
+                    Err(error) => {
+                        app.diagnostic = Some(Diagnostic::without_position(format!("Cannot paste the states: {}", error)))
+                    }
+                }
+            }
+        }
+    }
+
+    if is_graph_changed {
+        app.layout.reheat();
+        app.graph_changed(ui.input(|input| input.time));
+    }
+}
+
+/// Copy, cut and paste the selected states through the system clipboard, unless a text is being edited.
+/// The states are pasted at the center of the graph displayed.
+fn clipboard(app: &mut TuringApp, ui: &mut Ui, edits: &mut Vec<GraphEdit>) {
+    if ui.ctx().memory(|memory| memory.focused().is_some()) {
+        return;
+    }
+
+    for event in ui.input(|input| input.events.clone()) {
+        match event {
+            Event::Copy | Event::Cut if !app.selection.selected_states.is_empty() => {
+                ui.ctx().copy_text(app.copy_states(&app.selection.selected_states));
+
+                if matches!(event, Event::Cut) {
+                    edits.push(GraphEdit::DeleteStates(app.selection.selected_states.iter().copied().collect()));
+                }
+            }
+            Event::Paste(text) => edits.push(GraphEdit::Paste(text)),
+            _ => {}
+        }
+    }
+}
+
+/// Select the states inside the box drawn by dragging the background with shift pressed,
+/// the states already selected stay selected.
+/// This function must be called before drawing the graph to keep the states interactive.
+fn select_box(app: &mut TuringApp, ui: &mut Ui) {
+    let is_selecting = app.selection.box_start.is_some() || ui.input(|input| input.modifiers.shift);
+    let sense = if is_selecting { Sense::drag() } else { Sense::hover() };
+    let response = ui.interact(ui.clip_rect(), ui.id().with("select_box"), sense);
+
+    if response.drag_started() {
+        app.selection.box_start = response.interact_pointer_pos();
+    }
+
+    let (Some(start), Some(end)) = (app.selection.box_start, response.interact_pointer_pos()) else {
+        return;
+    };
+    let rect = Rect::from_two_pos(start, end);
+
+    ui.painter().rect(
+        rect,
+        CornerRadius::ZERO,
+        Constant::SELECTED.gamma_multiply(0.1),
+        Stroke::new(1.0, Constant::SELECTED),
+        StrokeKind::Inside,
+    );
+
+    if response.drag_stopped() {
+        app.selection.box_start = None;
+        app.selection.selected_transition = None;
+        app.selection.is_editing = false;
+        app.selection.selected_states.extend(
+            app.states.values().filter(|state| rect.contains(state.position)).map(|state| state.id),
+        );
+    }
+}
+
 /// draw the transitions between states.
 /// This function must be called before draw_states to display the states to the top layer.
-fn draw_transitions(app: &mut TuringApp, ui: &mut Ui) {
+fn draw_transitions(app: &mut TuringApp, ui: &mut Ui, edits: &mut Vec<GraphEdit>) {
+    // the transition that produced the step displayed is highlighted
+    let fired = app.history.current().transition;
+
+    let mut changed_offsets: Vec<((u8, u8), CurveOffset)> = vec![];
+    for edge in edges(&mut app.states, &app.turing.turing_machine, &mut app.edge_cache, ui) {
+        if let Some(offset) = draw_curve_transitions(&mut app.selection, ui, &edge.curve, edge.offset, edge.transitions, edge.label.center(), fired, edits) {
+            changed_offsets.push((edge.ids, offset));
+        }
+    }
+
+    // save the offsets in the source state, by name of the target
+    for ((from, to), offset) in changed_offsets {
+        let target_name = app.states[&to].name.to_string();
+        if let Some(state) = app.states.get_mut(&from) {
+            state.curves.insert(target_name, offset);
+        }
+        app.is_dirty = true;
+    }
+}
+
+/// The curve of the transitions from a state to another and the rect of their labels
+pub struct Edge<'a> {
+    /// The index of the source and of the target state
+    pub ids: (u8, u8),
+    pub curve: Curve,
+    pub offset: CurveOffset,
+    pub label: Rect,
+    pub transitions: Vec<&'a mut Transition>,
+}
+
+/// The curves and the labels of the last graph computed, by index of the source and of the target state.
+/// They are computed again only when a state moved or a transition changed.
+#[derive(Default)]
+pub struct EdgeCache {
+    key: u64,
+    geometry: HashMap<(u8, u8), (Curve, Rect)>,
+}
+
+/// Compute the curves of the graph, grouping the transitions by source and target.
+/// The curves are routed around the states in the way, then moved by the offset chosen by the user,
+/// and the labels are moved apart from each other.
+pub fn edges<'a>(states: &'a mut HashMap<u8, State>, turing_machine: &TuringMachine, cache: &mut EdgeCache, ui: &Ui) -> Vec<Edge<'a>> {
     // group transition by (source, target) index
     let mut transitions_hashmap: HashMap<(u8, u8), Vec<&mut Transition>> = HashMap::new();
 
     let mut state_position: HashMap<u8, Pos2> = HashMap::new();
 
     let mut graph_center = Vec2::ZERO;
-    let states_count = app.states.len();
+    let states_count = states.len();
+
+    // the offsets of the curves chosen by the user, by (source, target) index
+    let ids_by_name: HashMap<&String, u8> = states.values().map(|state| (&state.name, state.id)).collect();
+    let offsets: HashMap<(u8, u8), CurveOffset> = states
+        .values()
+        .flat_map(|state| {
+            state.curves
+                .iter()
+                .filter_map(|(name, offset)| ids_by_name.get(name).map(|target| ((state.id, *target), *offset)))
+        })
+        .collect();
 
     // iterate all states to find transitions
-    for (index, state) in app.states.iter_mut() {
+    for (index, state) in states.iter_mut() {
         state_position.insert(*index, state.position);
 
         // each state hold all transitions of which its the source
         for transition in state.transitions.iter_mut() {
 
             // get TuringTransition to acquire the nex state id
-            let target_state_index = app.turing.turing_machine
+            let target_state_index = turing_machine
                 .states[*index as usize]
                 .transitions[transition.id as usize]
                 .index_to_state;
@@ -95,41 +344,73 @@ fn draw_transitions(app: &mut TuringApp, ui: &mut Ui) {
 
     graph_center /= states_count as f32;
 
+    let edges: Vec<((u8, u8), Vec<&mut Transition>)> = transitions_hashmap.into_iter().sorted_by_key(|f| f.0).collect();
+    let sizes: Vec<Vec2> = edges.iter().map(|(_, transitions)| labels_size(ui, transitions)).collect();
 
-    // iterate each group of transitions
-    for ((from, to), transitions) in transitions_hashmap.iter_mut().sorted_by_key(|f| f.0) {
-        // vector of transition and a boolean
-        let mut transition_rules: Vec<&mut Transition> = vec![];
+    // everything the curves and the labels depend on
+    let mut hasher = DefaultHasher::new();
+    for (id, position) in state_position.iter().sorted_by_key(|(id, _)| **id) {
+        (id, position.x.to_bits(), position.y.to_bits()).hash(&mut hasher);
+    }
+    for ((ids, transitions), size) in edges.iter().zip(sizes.iter()) {
+        let offset = offsets.get(ids).copied().unwrap_or_default();
+        (ids, size.x.to_bits(), size.y.to_bits()).hash(&mut hasher);
+        transitions.iter().for_each(|transition| transition.text.hash(&mut hasher));
+        [offset.control.x, offset.control.y, offset.label.x, offset.label.y].map(f32::to_bits).hash(&mut hasher);
+    }
+    let key = hasher.finish();
 
-        for transition in transitions {
-            transition_rules.push(transition);
-        }
+    if cache.key != key {
+        let grid = Grid::new(state_position.iter().map(|(id, position)| (*id, *position)));
+        let mut geometry: Vec<((u8, u8), Curve, Rect, bool)> = vec![];
 
-        let source_position = state_position.get(&from).unwrap();
-        let target_position = state_position.get(&to).unwrap();
-
-        if from == to {
-            // draw loop transition for same state source/target
-            draw_loop_transitions(
-                &mut app.selection,
-                ui,
-                *source_position,
-                transition_rules,
-                graph_center,
-            );
-        } else {
-
-            let reverse = app.turing.turing_machine.get_transition_index(*to, *from).is_some_and(|_| to > from);
-            // draw normal bezier
-            draw_normal_transitions(
-                &mut app.selection,
-                ui,
-                *source_position,
-                *target_position,
-                transition_rules,
-                graph_center,
-                reverse
-            );
+        // compute the curve of each group of transitions
+        for (((from, to), transitions), size) in edges.iter().zip(sizes.iter()) {
+            let (from, to) = (*from, *to);
+            let source_position = state_position[&from];
+            let target_position = state_position[&to];
+            let offset = offsets.get(&(from, to)).copied().unwrap_or_default();
+
+            let (curve, label_position) = if from == to {
+                let (mut points, label_position) = loop_curve(source_position, graph_center);
+                points[1] += offset.control;
+                points[2] += offset.control;
+
+                // the middle of the loop moves by three quarters of the move of its control points
+                (Curve::Loop(points), label_position + offset.control * 0.75)
+            } else {
+                let reverse = turing_machine.get_transition_index(to, from).is_some_and(|_| to > from);
+                let (points, label_position) = normal_curve(ui, source_position, target_position, transitions, graph_center, reverse);
+                let mut routed = route_around(points, &grid);
+                routed[1] += offset.control;
+
+                // the middle of the curve moves by half the move of its control point
+                (Curve::Normal(routed), label_position + (routed[1] - points[1]) / 2.0)
+            };
+
+            let label = Rect::from_center_size(label_position + offset.label, *size);
+            geometry.push(((from, to), curve, label, offset.label != Vec2::ZERO));
         }
+
+        // move the labels apart from each other and from the states, the labels moved by the user stay in place
+        let mut labels: Vec<Rect> = geometry.iter().map(|(_, _, label, _)| *label).collect();
+        let is_fixed: Vec<bool> = geometry.iter().map(|(_, _, _, is_fixed)| *is_fixed).collect();
+        place_labels(&mut labels, &is_fixed, &grid);
+
+        cache.key = key;
+        cache.geometry = geometry
+            .into_iter()
+            .zip(labels)
+            .map(|((ids, curve, _, _), label)| (ids, (curve, label)))
+            .collect();
     }
-}
\ No newline at end of file
+
+    edges
+        .into_iter()
+        .filter_map(|(ids, transitions)| {
+            let (curve, label) = *cache.geometry.get(&ids)?;
+            let offset = offsets.get(&ids).copied().unwrap_or_default();
+            Some(Edge { ids, curve, offset, label, transitions })
+        })
+        .collect()
+}
@@ -0,0 +1,480 @@
+use std::{fmt::Write, path::Path};
+
+use ab_glyph::{Font, FontRef, GlyphId, OutlineCurve, PxScale, PxScaleFont, ScaleFont};
+use egui::{Color32, FontFamily, FontId, Pos2, Rect, Stroke, Ui, Vec2, vec2};
+use itertools::Itertools;
+use poll_promise::Promise;
+use tiny_skia::{FillRule, Paint, Path as SkiaPath, PathBuilder, Pixmap, Transform};
+
+use crate::{TuringApp, app::{Diagnostic, machine, xml::escape}, ui::{constant::Constant, utils::constrast_color}};
+
+use super::{edges, state::initial_arrow, transition::{Curve, arrow}};
+
+const ROBOTO: &[u8] = include_bytes!("../../../assets/fonts/Roboto.ttf");
+const ROBOTO_MONO: &[u8] = include_bytes!("../../../assets/fonts/RobotoMono-Regular.ttf");
+
+/// A shape of the graph as drawn in the scene, without the selection and the execution highlights
+enum Shape {
+    Circle { center: Pos2, radius: f32, fill: Color32, stroke: Stroke },
+    Line { points: [Pos2; 2], stroke: Stroke },
+    Quadratic { points: [Pos2; 3], stroke: Stroke },
+    Cubic { points: [Pos2; 4], stroke: Stroke },
+    Polygon { points: Vec<Pos2>, fill: Color32 },
+    /// A single line of text centered on the position
+    Text { center: Pos2, text: String, font: FontId, color: Color32 },
+}
+
+impl Shape {
+    /// The rect containing the shape
+    fn rect(&self) -> Rect {
+        match self {
+            Shape::Circle { center, radius, stroke, .. } => {
+                Rect::from_center_size(*center, Vec2::splat(radius * 2.0 + stroke.width))
+            }
+            Shape::Line { points, stroke } => Rect::from_points(points).expand(stroke.width / 2.0),
+            // a bezier is contained in the hull of its control points
+            Shape::Quadratic { points, stroke } => Rect::from_points(points).expand(stroke.width / 2.0),
+            Shape::Cubic { points, stroke } => Rect::from_points(points).expand(stroke.width / 2.0),
+            Shape::Polygon { points, .. } => Rect::from_points(points),
+            Shape::Text { center, text, font, .. } => {
+                let font = scaled_font(font);
+                let (_, width) = layout_text(&font, text);
+                Rect::from_center_size(*center, vec2(width, font.ascent() - font.descent()))
+            }
+        }
+    }
+}
+
+/// The menu to export the graph as an image, as a TikZ picture or as a Graphviz graph.
+/// The export holds the whole graph, whatever the part of the graph displayed.
+pub fn menu(app: &mut TuringApp, ui: &mut Ui) {
+    // the export is forgotten once the file dialog is closed, its error is reported
+    if app.export_promise.as_ref().is_some_and(|promise| promise.ready().is_some()) {
+        if let Some(Err(error)) = app.export_promise.take().map(|promise| promise.block_and_take()) {
+            app.diagnostic = Some(Diagnostic::without_position(format!("Cannot export the graph: {}", error)));
+        }
+    }
+
+    ui.add_enabled_ui(app.export_promise.is_none(), |ui| {
+        ui.menu_button("Export", |ui| {
+            if ui.button("SVG").clicked() {
+                let svg = to_svg(&shapes(app, ui));
+                save(app, "svg", svg.into_bytes());
+                ui.close_menu();
+            }
+
+            if ui.button("PNG").clicked() {
+                match to_png(&shapes(app, ui)) {
+                    Ok(png) => save(app, "png", png),
+                    Err(error) => app.diagnostic = Some(Diagnostic::without_position(format!("Cannot export the graph: {}", error))),
+                }
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            if ui.button("TikZ").clicked() {
+                let tikz = app.to_tikz();
+                save(app, "tex", tikz.into_bytes());
+                ui.close_menu();
+            }
+
+            if ui.button("DOT").clicked() {
+                let dot = app.to_dot();
+                save(app, "dot", dot.into_bytes());
+                ui.close_menu();
+            }
+        });
+    });
+}
+
+/// The shapes of the graph, the transitions first to draw the states above them
+fn shapes(app: &mut TuringApp, ui: &Ui) -> Vec<Shape> {
+    let mut shapes: Vec<Shape> = vec![];
+
+    let font_height = Constant::get_heigt(ui, &Constant::default_font());
+    let curve_stroke = Stroke::new(Constant::TRANSITION_THICKNESS, Constant::PRIMARY_COLOR);
+
+    for edge in edges(&mut app.states, &app.turing.turing_machine, &mut app.edge_cache, ui) {
+        shapes.push(match edge.curve {
+            Curve::Normal(points) => Shape::Quadratic { points, stroke: curve_stroke },
+            Curve::Loop(points) => Shape::Cubic { points, stroke: curve_stroke },
+        });
+        shapes.push(Shape::Polygon { points: arrow(&edge.curve).to_vec(), fill: Constant::PRIMARY_COLOR });
+
+        // the labels are stacked one above the other, as in the graph
+        let height_used = edge.transitions.len() as f32 * font_height;
+        for (i, transition) in edge.transitions.iter().enumerate() {
+            shapes.push(Shape::Text {
+                center: edge.label.center() + vec2(0.0, (i as f32 + 0.5) * font_height - height_used / 2.0),
+                text: transition.text.to_string(),
+                font: Constant::default_font(),
+                color: Constant::PRIMARY_COLOR,
+            });
+        }
+    }
+
+    for state in app.states.values().sorted_by_key(|state| state.id) {
+        let contrast = constrast_color(state.color);
+
+        shapes.push(Shape::Circle {
+            center: state.position,
+            radius: Constant::STATE_RADIUS,
+            fill: state.color,
+            stroke: Stroke::new(3.0, contrast),
+        });
+
+        if app.turing.turing_machine.states[state.id as usize].is_final {
+            shapes.push(Shape::Circle {
+                center: state.position,
+                radius: Constant::STATE_RADIUS - Constant::ACCEPTING_RING_GAP,
+                fill: Color32::TRANSPARENT,
+                stroke: Stroke::new(2.0, contrast),
+            });
+        }
+
+        if machine::is_initial(state.id) {
+            let (line, head) = initial_arrow(state.position);
+            shapes.push(Shape::Line {
+                points: line,
+                stroke: Stroke::new(Constant::TRANSITION_THICKNESS * 2.0, Constant::PRIMARY_COLOR),
+            });
+            shapes.push(Shape::Polygon { points: head.to_vec(), fill: Constant::PRIMARY_COLOR });
+        }
+
+        shapes.push(Shape::Text {
+            center: state.position,
+            text: state.name.to_string(),
+            font: Constant::big_font(),
+            color: contrast,
+        });
+    }
+
+    shapes
+}
+
+/// The rect of the image, containing every shape with a margin around them
+fn bounds(shapes: &[Shape]) -> Rect {
+    let rect = shapes.iter().fold(Rect::NOTHING, |rect, shape| rect.union(shape.rect()));
+
+    if rect.is_negative() {
+        Rect::ZERO.expand(Constant::EXPORT_MARGIN)
+    } else {
+        rect.expand(Constant::EXPORT_MARGIN)
+    }
+}
+
+/// Write the shapes in a standalone SVG document, in the coordinates of the scene
+fn to_svg(shapes: &[Shape]) -> String {
+    let rect = bounds(shapes);
+    let mut svg = String::new();
+
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xml:space="preserve" width="{:.0}" height="{:.0}" viewBox="{:.2} {:.2} {:.2} {:.2}">"#,
+        rect.width().ceil(), rect.height().ceil(), rect.min.x, rect.min.y, rect.width(), rect.height(),
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}"/>"#,
+        rect.min.x, rect.min.y, rect.width(), rect.height(), svg_color(Constant::BACKGROUND_2),
+    );
+
+    for shape in shapes {
+        let _ = match shape {
+            Shape::Circle { center, radius, fill, stroke } => writeln!(
+                svg,
+                r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="{}" {}/>"#,
+                center.x, center.y, radius, svg_color(*fill), svg_stroke(*stroke),
+            ),
+            Shape::Line { points: [a, b], stroke } => writeln!(
+                svg,
+                r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" {}/>"#,
+                a.x, a.y, b.x, b.y, svg_stroke(*stroke),
+            ),
+            Shape::Quadratic { points: [a, b, c], stroke } => writeln!(
+                svg,
+                r#"<path d="M {:.2} {:.2} Q {:.2} {:.2} {:.2} {:.2}" fill="none" {}/>"#,
+                a.x, a.y, b.x, b.y, c.x, c.y, svg_stroke(*stroke),
+            ),
+            Shape::Cubic { points: [a, b, c, d], stroke } => writeln!(
+                svg,
+                r#"<path d="M {:.2} {:.2} C {:.2} {:.2} {:.2} {:.2} {:.2} {:.2}" fill="none" {}/>"#,
+                a.x, a.y, b.x, b.y, c.x, c.y, d.x, d.y, svg_stroke(*stroke),
+            ),
+            Shape::Polygon { points, fill } => writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{}"/>"#,
+                points.iter().map(|point| format!("{:.2},{:.2}", point.x, point.y)).join(" "),
+                svg_color(*fill),
+            ),
+            Shape::Text { center, text, font, color } => {
+                let scaled = scaled_font(font);
+                writeln!(
+                    svg,
+                    r#"<text x="{:.2}" y="{:.2}" font-family="{}" font-size="{}" fill="{}" text-anchor="middle">{}</text>"#,
+                    center.x, baseline(&scaled, *center), svg_family(font), font.size, svg_color(*color), escape(text),
+                )
+            }
+        };
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// The color as written in SVG, a transparent color is not painted
+fn svg_color(color: Color32) -> String {
+    if color.a() == 0 {
+        return "none".to_string();
+    }
+
+    let [r, g, b, _] = color.to_srgba_unmultiplied();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn svg_stroke(stroke: Stroke) -> String {
+    format!(r#"stroke="{}" stroke-width="{}""#, svg_color(stroke.color), stroke.width)
+}
+
+/// The font families of the text, with a generic family if the font is not installed
+fn svg_family(font: &FontId) -> &'static str {
+    match &font.family {
+        FontFamily::Name(name) if name.as_ref() == "Roboto" => "Roboto, sans-serif",
+        _ => "Roboto Mono, monospace",
+    }
+}
+
+/// Rasterize the shapes in a PNG image, scaled by `Constant::EXPORT_PNG_SCALE`.
+/// The texts are drawn with the fonts of the application.
+fn to_png(shapes: &[Shape]) -> Result<Vec<u8>, String> {
+    let rect = bounds(shapes);
+    let scale = Constant::EXPORT_PNG_SCALE;
+    let size = rect.size() * scale;
+
+    let mut pixmap = Pixmap::new(size.x.ceil() as u32, size.y.ceil() as u32)
+        .ok_or("The graph is too large to be exported")?;
+    let [r, g, b, a] = Constant::BACKGROUND_2.to_srgba_unmultiplied();
+    pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, a));
+
+    let transform = Transform::from_scale(scale, scale).pre_translate(-rect.min.x, -rect.min.y);
+
+    for shape in shapes {
+        match shape {
+            Shape::Circle { center, radius, fill, stroke } => {
+                if let Some(path) = PathBuilder::from_circle(center.x, center.y, *radius) {
+                    fill_path(&mut pixmap, &path, *fill, transform);
+                    stroke_path(&mut pixmap, &path, *stroke, transform);
+                }
+            }
+            Shape::Line { points: [a, b], stroke } => {
+                let mut builder = PathBuilder::new();
+                builder.move_to(a.x, a.y);
+                builder.line_to(b.x, b.y);
+                if let Some(path) = builder.finish() {
+                    stroke_path(&mut pixmap, &path, *stroke, transform);
+                }
+            }
+            Shape::Quadratic { points: [a, b, c], stroke } => {
+                let mut builder = PathBuilder::new();
+                builder.move_to(a.x, a.y);
+                builder.quad_to(b.x, b.y, c.x, c.y);
+                if let Some(path) = builder.finish() {
+                    stroke_path(&mut pixmap, &path, *stroke, transform);
+                }
+            }
+            Shape::Cubic { points: [a, b, c, d], stroke } => {
+                let mut builder = PathBuilder::new();
+                builder.move_to(a.x, a.y);
+                builder.cubic_to(b.x, b.y, c.x, c.y, d.x, d.y);
+                if let Some(path) = builder.finish() {
+                    stroke_path(&mut pixmap, &path, *stroke, transform);
+                }
+            }
+            Shape::Polygon { points, fill } => {
+                let mut builder = PathBuilder::new();
+                for (i, point) in points.iter().enumerate() {
+                    if i == 0 {
+                        builder.move_to(point.x, point.y);
+                    } else {
+                        builder.line_to(point.x, point.y);
+                    }
+                }
+                builder.close();
+                if let Some(path) = builder.finish() {
+                    fill_path(&mut pixmap, &path, *fill, transform);
+                }
+            }
+            Shape::Text { center, text, font, color } => {
+                if let Some(path) = text_path(*center, text, font) {
+                    fill_path(&mut pixmap, &path, *color, transform);
+                }
+            }
+        }
+    }
+
+    pixmap.encode_png().map_err(|error| error.to_string())
+}
+
+fn paint(color: Color32) -> Paint<'static> {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+    paint.anti_alias = true;
+    paint
+}
+
+fn fill_path(pixmap: &mut Pixmap, path: &SkiaPath, color: Color32, transform: Transform) {
+    if color.a() > 0 {
+        pixmap.fill_path(path, &paint(color), FillRule::Winding, transform, None);
+    }
+}
+
+fn stroke_path(pixmap: &mut Pixmap, path: &SkiaPath, stroke: Stroke, transform: Transform) {
+    if !stroke.is_empty() {
+        let width = tiny_skia::Stroke { width: stroke.width, ..Default::default() };
+        pixmap.stroke_path(path, &paint(stroke.color), &width, transform, None);
+    }
+}
+
+/// The outlines of the glyphs of the text, centered on the position
+fn text_path(center: Pos2, text: &str, font: &FontId) -> Option<SkiaPath> {
+    let font = scaled_font(font);
+    let (glyphs, width) = layout_text(&font, text);
+    let origin = Pos2::new(center.x - width / 2.0, baseline(&font, center));
+    let (h_scale, v_scale) = (font.h_scale_factor(), font.v_scale_factor());
+
+    let mut builder = PathBuilder::new();
+    for (id, x) in glyphs {
+        let Some(outline) = font.font().outline(id) else {
+            continue;
+        };
+
+        // the outlines are in the units of the font, going up from the baseline
+        let point = |point: ab_glyph::Point| (origin.x + x + point.x * h_scale, origin.y - point.y * v_scale);
+
+        // the curves of a contour follow each other, a curve not starting at the end of the previous one starts a new contour
+        let mut last = None;
+        for curve in outline.curves {
+            let (start, end) = match curve {
+                OutlineCurve::Line(start, end) => (start, end),
+                OutlineCurve::Quad(start, _, end) => (start, end),
+                OutlineCurve::Cubic(start, _, _, end) => (start, end),
+            };
+
+            if last != Some(start) {
+                if last.is_some() {
+                    builder.close();
+                }
+                let (x, y) = point(start);
+                builder.move_to(x, y);
+            }
+
+            match curve {
+                OutlineCurve::Line(_, end) => {
+                    let (x, y) = point(end);
+                    builder.line_to(x, y);
+                }
+                OutlineCurve::Quad(_, control, end) => {
+                    let ((x1, y1), (x, y)) = (point(control), point(end));
+                    builder.quad_to(x1, y1, x, y);
+                }
+                OutlineCurve::Cubic(_, control1, control2, end) => {
+                    let ((x1, y1), (x2, y2), (x, y)) = (point(control1), point(control2), point(end));
+                    builder.cubic_to(x1, y1, x2, y2, x, y);
+                }
+            }
+            last = Some(end);
+        }
+
+        if last.is_some() {
+            builder.close();
+        }
+    }
+
+    builder.finish()
+}
+
+/// The font file of the font, scaled as egui does
+fn scaled_font(font: &FontId) -> PxScaleFont<FontRef<'static>> {
+    let data = match &font.family {
+        FontFamily::Name(name) if name.as_ref() == "Roboto" => ROBOTO,
+        _ => ROBOTO_MONO,
+    };
+    let font_file = FontRef::try_from_slice(data).expect("cannot read font");
+    let scale = font_file.pt_to_px_scale(font.size).unwrap_or(PxScale::from(font.size));
+
+    font_file.into_scaled(scale)
+}
+
+/// The glyphs of the text with their position from the start of the text, and the width of the text
+fn layout_text(font: &PxScaleFont<FontRef<'static>>, text: &str) -> (Vec<(GlyphId, f32)>, f32) {
+    let mut glyphs = vec![];
+    let mut x = 0.0;
+    let mut previous: Option<GlyphId> = None;
+
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            x += font.kern(previous, id);
+        }
+        glyphs.push((id, x));
+        x += font.h_advance(id);
+        previous = Some(id);
+    }
+
+    (glyphs, x)
+}
+
+/// The height of the baseline of a text vertically centered on the position
+fn baseline(font: &PxScaleFont<FontRef<'static>>, center: Pos2) -> f32 {
+    center.y + (font.ascent() + font.descent()) / 2.0
+}
+
+/// Ask where to save the export, with the name of the current file
+#[cfg(not(target_arch = "wasm32"))]
+fn save(app: &mut TuringApp, extension: &'static str, content: Vec<u8>) {
+    use std::fs;
+
+    use rfd::FileDialog;
+
+    let file_name = export_name(app, extension);
+    app.export_promise = Some(Promise::spawn_thread("export_file", move || {
+        let Some(path) = FileDialog::new()
+            .add_filter(extension, &[extension])
+            .set_file_name(file_name)
+            .save_file()
+        else {
+            return Ok(());
+        };
+
+        fs::write(&path, content).map_err(|error| format!("cannot write file {}: {}", path.display(), error))
+    }));
+}
+
+/// Download the export, the browser ask where to save it
+#[cfg(target_arch = "wasm32")]
+fn save(app: &mut TuringApp, extension: &'static str, content: Vec<u8>) {
+    use rfd::AsyncFileDialog;
+
+    let file_name = export_name(app, extension);
+    app.export_promise = Some(Promise::spawn_local(async move {
+        let Some(file) = AsyncFileDialog::new()
+            .add_filter(extension, &[extension])
+            .set_file_name(file_name)
+            .save_file()
+            .await
+        else {
+            return Ok(());
+        };
+
+        file.write(&content)
+            .await
+            .map_err(|error| format!("cannot write file {}: {}", file.file_name(), error))
+    }));
+}
+
+/// The name of the current file with the extension of the export
+fn export_name(app: &TuringApp, extension: &str) -> String {
+    Path::new(&app.file_name()).with_extension(extension).to_string_lossy().to_string()
+}
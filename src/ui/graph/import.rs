@@ -0,0 +1,50 @@
+use egui::Ui;
+use poll_promise::Promise;
+
+use crate::{TuringApp, app::Diagnostic};
+
+/// The button to move the states to the positions of a DOT file, as laid out by Graphviz
+pub fn positions_button(app: &mut TuringApp, ui: &mut Ui) {
+    if app.import_promise.as_ref().is_some_and(|promise| promise.ready().is_some()) {
+        let content = app.import_promise.take().and_then(|promise| promise.block_and_take());
+
+        let imported = content.map(|content| content.and_then(|content| app.import_dot_positions(&content)));
+        if let Some(Err(error)) = imported {
+            app.diagnostic = Some(Diagnostic::without_position(format!("Cannot import the positions: {}", error)));
+        }
+    }
+
+    let response = ui
+        .add_enabled(app.import_promise.is_none(), egui::Button::new("Import positions"))
+        .on_hover_text("Move the states to the positions of the nodes of a DOT file");
+
+    if response.clicked() {
+        app.import_promise = Some(read_file(&["dot", "gv"]));
+    }
+}
+
+/// Ask for a file and read it, nothing is read if no file is picked
+#[cfg(not(target_arch = "wasm32"))]
+fn read_file(extensions: &'static [&'static str]) -> Promise<Option<Result<String, String>>> {
+    use std::fs;
+
+    use rfd::FileDialog;
+
+    Promise::spawn_thread("import_file", move || {
+        let path = FileDialog::new().add_filter("ext", extensions).pick_file()?;
+
+        Some(fs::read_to_string(&path).map_err(|error| format!("cannot read file {}: {}", path.display(), error)))
+    })
+}
+
+/// Ask for a file and read it, the browser ask which file to upload
+#[cfg(target_arch = "wasm32")]
+fn read_file(extensions: &'static [&'static str]) -> Promise<Option<Result<String, String>>> {
+    use rfd::AsyncFileDialog;
+
+    Promise::spawn_local(async move {
+        let file = AsyncFileDialog::new().add_filter("ext", extensions).pick_file().await?;
+
+        Some(String::from_utf8(file.read().await).map_err(|error| format!("cannot read file {}: {}", file.file_name(), error)))
+    })
+}
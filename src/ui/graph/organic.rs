@@ -1,60 +1,119 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use egui::Vec2;
+use egui::{Pos2, Rect, Vec2};
 
 use crate::{ui::{constant::Constant, utils}, TuringApp};
 
+/// Move the states with a force directed simulation and return true once they stopped moving.
+/// The states linked by a transition attract each other and the other states closer than
+/// `Constant::L` repulse each other, so only the states in the neighbouring cells of a grid of that size are compared.
+/// The speed of the states is damped and the simulation cools down until the states stop moving.
 pub fn apply_force(app: &mut TuringApp) -> bool {
+    if app.layout.temperature < Constant::MIN_TEMPERATURE {
+        return true;
+    }
 
+    // the pairs of states linked by a transition, in any direction
+    let adjacent: HashSet<(u8, u8)> = app.turing.turing_machine.states
+        .iter()
+        .enumerate()
+        .flat_map(|(from, state)| state.transitions.iter().map(move |transition| pair(from as u8, transition.index_to_state)))
+        .filter(|(a, b)| a != b)
+        .collect();
 
-    let mut forces: HashMap<u8, Vec2> = HashMap::new();
+    let positions: HashMap<u8, Pos2> = app.states.iter().map(|(id, state)| (*id, state.position)).collect();
 
-    // register the max force applied on a state to check if the system is stable
-    let mut max_force_applied:f32 = 0.0;
+    let grid = Grid::new(positions.iter().map(|(id, position)| (*id, *position)));
 
-    for (i, state_1) in app.states.iter() {
-        
-        let mut force :f32 = 0.0;
-        let mut final_force: Vec2 = Vec2::ZERO;
+    let mut forces: HashMap<u8, Vec2> = positions.keys().map(|id| (*id, Vec2::ZERO)).collect();
 
-        for (j, state_2) in app.states.iter() {
+    // attraction between the states linked by a transition
+    for (a, b) in adjacent.iter() {
+        let (Some(position_a), Some(position_b)) = (positions.get(a), positions.get(b)) else {
+            continue;
+        };
 
-            // continue if it's the same state
-            if j == i { continue; }
+        let force = utils::direction(*position_a, *position_b) * utils::attract_force(*position_a, *position_b);
+        *forces.get_mut(a).unwrap() += force;
+        *forces.get_mut(b).unwrap() -= force;
+    }
 
-            // true if there is a transition between the two states
-            let are_adjacent = app.turing.turing_machine.get_transition_index(*i, *j).is_some()
-                || app.turing.turing_machine.get_transition_index(*j, *i).is_some();
-                
+    // repulsion between the close states without transition between them
+    for (id, position) in positions.iter() {
+        let neighbours = grid.within(Rect::from_center_size(*position, Vec2::splat(Constant::L * 2.0)));
 
-            let distance = utils::distance(state_1.position, state_2.position);
-            let direction = utils::direction(state_1.position, state_2.position);
+        for (other, other_position) in neighbours {
+            if other == id || adjacent.contains(&pair(*id, *other)) {
+                continue;
+            }
 
-            // different equations are use based on the adjacency of the states
-            if are_adjacent {
-                force = utils::attract_force(state_1.position, state_2.position);
+            let other_position = *other_position;
+            if utils::distance(*position, other_position) < Constant::L {
+                *forces.get_mut(id).unwrap() -= utils::direction(*position, other_position) * utils::rep_force(*position, other_position);
             }
-            else if distance < Constant::L {
-                force = -utils::rep_force(state_1.position, state_2.position);
-            };
+        }
+    }
+
+    // move the states, the highest move is kept to check if the system is stable
+    let temperature = app.layout.temperature;
+    let mut max_move: f32 = 0.0;
+
+    for (id, state) in app.states.iter_mut() {
+        if state.is_pinned {
+            state.velocity = Vec2::ZERO;
+            continue;
+        }
 
-            // apply the force on the final force vector
-            final_force += direction * force;
+        state.velocity = (state.velocity + forces[id]) * Constant::DAMPING;
+
+        let mut step = state.velocity * temperature;
+        if step.length() > Constant::MAX_FORCE {
+            step = step.normalized() * Constant::MAX_FORCE;
         }
 
-        // save the highest force applied
-        if force > max_force_applied {
-            max_force_applied = force;
+        state.position += step;
+        max_move = max_move.max(step.length());
+    }
+
+    app.layout.temperature *= Constant::COOLING;
+
+    max_move < Constant::STABILITY_TRESHOLD
+}
+
+/// The pair of states in the same order whatever the direction
+fn pair(a: u8, b: u8) -> (u8, u8) {
+    (a.min(b), a.max(b))
+}
+
+/// The states by cell of a grid, to find the states close to a position without comparing every state.
+/// The cells are as large as the repulsion distance.
+pub struct Grid {
+    cells: HashMap<(i32, i32), Vec<(u8, Pos2)>>,
+}
+
+impl Grid {
+    pub fn new(states: impl Iterator<Item = (u8, Pos2)>) -> Self {
+        let mut cells: HashMap<(i32, i32), Vec<(u8, Pos2)>> = HashMap::new();
+        for (id, position) in states {
+            cells.entry(cell(position)).or_default().push((id, position));
         }
 
-        // store the compute force to not alter the current physical state
-        forces.insert(*i, final_force);
+        Self { cells }
     }
 
-    for (i, state) in app.states.iter_mut() {
-        // translate the state by the amount of force
-        state.position += *forces.get(&i).unwrap();
+    /// The states in the cells overlapping the rect, some of them may be outside of it
+    pub fn within(&self, rect: Rect) -> impl Iterator<Item = &(u8, Pos2)> {
+        let (min_x, min_y) = cell(rect.min);
+        let (max_x, max_y) = cell(rect.max);
+
+        (min_x..=max_x)
+            .flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+            .filter_map(|key| self.cells.get(&key))
+            .flatten()
     }
+}
 
-    max_force_applied < Constant::STABILITY_TRESHOLD
-}
\ No newline at end of file
+/// The cell of the grid containing the position
+fn cell(position: Pos2) -> (i32, i32) {
+    ((position.x / Constant::L).floor() as i32, (position.y / Constant::L).floor() as i32)
+}
@@ -0,0 +1,110 @@
+use egui::{Color32, Pos2, Rect, Stroke, Vec2, epaint::QuadraticBezierShape, vec2};
+
+use crate::ui::constant::Constant;
+
+use super::organic::Grid;
+
+/// Bend the curve between 2 states around the other states it passes through.
+/// The middle control point is pushed away from the closest state crossed, until the curve clears it.
+/// Only the states of the grid around the curve are checked.
+pub fn route_around(mut points: [Pos2; 3], states: &Grid) -> [Pos2; 3] {
+    let clearance = Constant::STATE_RADIUS + Constant::ROUTING_MARGIN;
+    let normal = (points[2] - points[0]).rot90().normalized();
+
+    for _ in 0..Constant::ROUTING_ITERATIONS {
+        // the curve is inside the rect of its control points
+        let around = Rect::from_points(&points).expand(clearance);
+        let crossed = states
+            .within(around)
+            .map(|(_, state)| *state)
+            .filter(|state| *state != points[0] && *state != points[2])
+            .map(|state| (state, distance_to_curve(points, state)))
+            .filter(|(_, distance)| *distance < clearance)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let Some((state, _)) = crossed else {
+            break;
+        };
+
+        // the control point moves further on its side of the state crossed
+        let side = if (points[1] - state).dot(normal) >= 0.0 { 1.0 } else { -1.0 };
+        points[1] += normal * side * Constant::ROUTING_STEP;
+    }
+
+    points
+}
+
+/// Move the labels so they don't overlap each other nor the states.
+/// The labels overlapping are pushed apart along the axis where they overlap the least,
+/// the labels over a state are pushed out of its circle.
+/// The fixed labels are not moved, the other labels are pushed away from them.
+pub fn place_labels(labels: &mut [Rect], is_fixed: &[bool], states: &Grid) {
+    for _ in 0..Constant::LABEL_PLACEMENT_ITERATIONS {
+        let mut is_moved = false;
+
+        for i in 0..labels.len() {
+            for j in i + 1..labels.len() {
+                let overlap = labels[i].intersect(labels[j]);
+                if overlap.width() <= 0.0 || overlap.height() <= 0.0 || (is_fixed[i] && is_fixed[j]) {
+                    continue;
+                }
+
+                let direction = labels[i].center() - labels[j].center();
+                let push = if overlap.width() < overlap.height() {
+                    vec2(overlap.width() / 2.0 * direction.x.signum(), 0.0)
+                } else {
+                    vec2(0.0, overlap.height() / 2.0 * direction.y.signum())
+                };
+
+                // the label not fixed is pushed the whole way
+                match (is_fixed[i], is_fixed[j]) {
+                    (true, _) => labels[j] = labels[j].translate(-push * 2.0),
+                    (_, true) => labels[i] = labels[i].translate(push * 2.0),
+                    _ => {
+                        labels[i] = labels[i].translate(push);
+                        labels[j] = labels[j].translate(-push);
+                    }
+                }
+                is_moved = true;
+            }
+
+            if is_fixed[i] {
+                continue;
+            }
+
+            let around = labels[i].expand(Constant::STATE_RADIUS);
+            for state in states.within(around).map(|(_, state)| state).filter(|state| around.contains(**state)) {
+                let outside = labels[i].clamp(*state) - *state;
+                let distance = outside.length();
+                if distance >= Constant::STATE_RADIUS {
+                    continue;
+                }
+
+                // the state center is inside the label, the label is pushed from its center
+                let direction = if distance > 0.0 {
+                    outside / distance
+                } else if labels[i].center() != *state {
+                    (labels[i].center() - *state).normalized()
+                } else {
+                    -Vec2::Y
+                };
+
+                labels[i] = labels[i].translate(direction * (Constant::STATE_RADIUS - distance + 1.0));
+                is_moved = true;
+            }
+        }
+
+        if !is_moved {
+            break;
+        }
+    }
+}
+
+/// The smallest distance between the position and points sampled on the curve
+fn distance_to_curve(points: [Pos2; 3], position: Pos2) -> f32 {
+    let curve = QuadraticBezierShape::from_points_stroke(points, false, Color32::TRANSPARENT, Stroke::NONE);
+
+    (0..=Constant::ROUTING_SAMPLES)
+        .map(|i| curve.sample(i as f32 / Constant::ROUTING_SAMPLES as f32).distance(position))
+        .fold(f32::INFINITY, f32::min)
+}
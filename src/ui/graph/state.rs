@@ -1,32 +1,88 @@
 
-use egui::{vec2, Align, Color32, Key, Label, Rect, Response, RichText, Sense, Stroke, TextEdit, Ui};
+use egui::{epaint::PathShape, vec2, Align, Align2, Color32, Key, Label, Pos2, Rect, Response, RichText, Sense, Stroke, TextEdit, Ui};
 use turingrs::turing_state::{TuringDirection, TuringTransition};
 
-use crate::{app::Selection, ui::{constant::Constant, turing::{State, Transition}, utils::constrast_color}, TuringApp};
+use crate::{app::{Selection, layout::Alignment, machine}, ui::{constant::Constant, turing::{State, Transition}, utils::constrast_color}, TuringApp};
 
+use super::GraphEdit;
 
-pub fn draw_states(app: &mut TuringApp, ui: &mut Ui) {
+
+/// Draw the states, a state is selected with a click and renamed with a double click.
+/// The new name is applied when the key enter is pressed.
+/// Clicking a state while another one is selected create a transition between them,
+/// a click with shift add the state to the selection instead.
+pub fn draw_states(app: &mut TuringApp, ui: &mut Ui, edits: &mut Vec<GraphEdit>) {
     
     // use of refcell means i can borrow one of the element of states without 
     // using a second loop
     let keys: Vec<u8> = app.states.keys().map(|u| *u).collect::<Vec<u8>>();
+
+    // the state of the step displayed is highlighted
+    let current_state = app.history.current().state;
+
     for i in keys {
 
         let state = app.states.get_mut(&i).unwrap();
 
+        let is_initial = machine::is_initial(i);
+        let is_final = app.turing.turing_machine.states[i as usize].is_final;
+
         let response = draw_node(
             &mut app.selection,
             ui,
             state,
-            false
+            i == current_state,
+            is_initial,
+            is_final
         );
 
+        // the bulk operations apply to the whole selection if the state is part of it
+        let targets: Vec<u8> = if app.selection.selected_states.contains(&i) {
+            app.selection.selected_states.iter().copied().collect()
+        } else {
+            vec![i]
+        };
+
+        response.context_menu(|ui| {
+            if targets.len() > 1 {
+                state_group_menu(ui, &targets, edits);
+                return;
+            }
+
+            let mut is_accepting = is_final;
+            if ui.checkbox(&mut is_accepting, "Accepting").clicked() {
+                edits.push(GraphEdit::ToggleAccepting(i));
+                ui.close_menu();
+            }
+            if ui.add_enabled(!is_initial, egui::Button::new("Set as initial")).clicked() {
+                edits.push(GraphEdit::SetInitial(i));
+                ui.close_menu();
+            }
+            if ui.add_enabled(!is_initial, egui::Button::new("Delete state")).clicked() {
+                edits.push(GraphEdit::DeleteStates(vec![i]));
+                ui.close_menu();
+            }
+            color_menu(ui, &targets, edits);
+        });
+
+        // if the current state is double clicked, rename it
+        if response.double_clicked() {
+            app.selection = Selection::rename_state(i, &state.name);
+        }
+        // if the current state is clicked with shift, add or remove it from the selection
+        else if response.clicked() && ui.input(|input| input.modifiers.shift) {
+            if !app.selection.selected_states.remove(&i) {
+                app.selection.selected_states.insert(i);
+            }
+            app.selection.selected_transition = None;
+            app.selection.is_editing = false;
+        }
         // if the current state is clicked
-        if response.clicked() {
+        else if response.clicked() {
 
-            // and a state is already selected, then create a transition between them
+            // and a single state is already selected, then create a transition between them
             // from selected to current state.
-            if let Some(selected) = app.selection.selected_state {
+            if let Some(selected) = app.selection.single_state() {
 
                 let transition = TuringTransition::new(
                     vec!['ç'; app.turing.turing_machine.k as usize + 1], 
@@ -50,51 +106,99 @@ pub fn draw_states(app: &mut TuringApp, ui: &mut Ui) {
                         text: transition_string,
                         id: transition_id,
                         parent_id: selected,
-                        target_id: i
+                        target_id: i,
                     }
                 );
 
-                app.selection.selected_state = None;
+                app.selection = Selection::default();
+                edits.push(GraphEdit::Edited);
                 
             } 
             // else select the current state 
             else {
-                app.selection.selected_state = Some(i);
-                app.selection.selected_transition = None;
+                app.selection = Selection::state(i);
             }
         }
 
-        // make the state follow the cursor when dragged
+        // make the state follow the cursor when dragged, with the other selected states.
+        // The states dragged are pinned, the others keep moving around them. The move can be undone once dropped
+        if response.drag_started() {
+            app.start_move(&targets);
+        }
         if response.dragged() {
-            app.states.get_mut(&i).unwrap().position = response.interact_pointer_pos().unwrap();
+            let delta = response.interact_pointer_pos().unwrap() - app.states[&i].position;
+            for id in targets.iter() {
+                if let Some(state) = app.states.get_mut(id) {
+                    state.position += delta;
+                    state.is_pinned = true;
+                }
+            }
+            app.layout.reheat();
         }
-
-        // remove the selection if the key enter is pressed.
-        // if the name is already taken add a number
-        if response.ctx.input(|input| {
-            input.key_pressed(Key::Enter)
-        }) {
-            if let Some(id) = app.selection.selected_state {
-                let mut state_name = app.states.get(&id).unwrap().name.to_string();
-
-                while app.states.iter().any(|(o_id, o_state)| { *o_id != id && o_state.name == state_name }) {
-                    state_name += "2";
+        if response.drag_stopped() {
+            for id in targets.iter() {
+                if let Some(state) = app.states.get_mut(id) {
+                    state.is_pinned = false;
                 }
-                app.states.get_mut(&id).unwrap().name = state_name;
-
-
             }
-            app.selection.selected_state = None
+            app.end_move();
+        }
+    }
+
+    // rename the state edited when the key enter is pressed, the key escape cancel the renaming
+    if let (Some(id), true) = (app.selection.single_state(), app.selection.is_editing) {
+        if ui.input(|input| input.key_pressed(Key::Enter)) {
+            edits.push(GraphEdit::Rename(id, app.selection.name_input.to_string()));
+        } else if ui.input(|input| input.key_pressed(Key::Escape)) {
+            app.selection = Selection::default();
         }
     }
 }
 
-/// Draw a single state
+/// The context menu of several selected states
+fn state_group_menu(ui: &mut Ui, ids: &[u8], edits: &mut Vec<GraphEdit>) {
+    if ui.button("Align horizontally").clicked() {
+        edits.push(GraphEdit::Align(ids.to_vec(), Alignment::Horizontal));
+        ui.close_menu();
+    }
+    if ui.button("Align vertically").clicked() {
+        edits.push(GraphEdit::Align(ids.to_vec(), Alignment::Vertical));
+        ui.close_menu();
+    }
+    if ui.button("Delete selection").clicked() {
+        edits.push(GraphEdit::DeleteStates(ids.to_vec()));
+        ui.close_menu();
+    }
+    color_menu(ui, ids, edits);
+}
+
+/// A sub menu to pick the color of states
+fn color_menu(ui: &mut Ui, ids: &[u8], edits: &mut Vec<GraphEdit>) {
+    ui.menu_button("Color", |ui| {
+        ui.horizontal(|ui| {
+            for color in Constant::STATE_COLORS {
+                let swatch = egui::Button::new("")
+                    .fill(color)
+                    .min_size(vec2(Constant::COLOR_SWATCH_SIZE, Constant::COLOR_SWATCH_SIZE));
+
+                if ui.add(swatch).clicked() {
+                    edits.push(GraphEdit::Recolor(ids.to_vec(), color));
+                    ui.close_menu();
+                }
+            }
+        });
+    });
+}
+
+/// Draw a single state.
+/// The accepting states have a double ring and the initial state an incoming arrow.
 fn draw_node(
     selection: &mut Selection,
     ui: &mut Ui,
     state: &mut State,
-    is_current: bool
+    is_current: bool,
+    is_initial: bool,
+    is_final: bool
 ) -> Response {
     
     let rect = Rect::from_center_size(
@@ -103,16 +207,29 @@ fn draw_node(
     );
 
     let color = if is_current {Constant::SELECTED} else {state.color};
+    let is_selected = selection.selected_states.contains(&state.id);
 
     ui.painter().circle(
         state.position, 
         Constant::STATE_RADIUS,
         color,
-        Stroke::new(3.0,constrast_color(color))
+        Stroke::new(3.0, if is_selected {Constant::SELECTED} else {constrast_color(color)})
     );
 
-    // if there is a state selected and it's the same as this one
-    if selection.selected_state.is_some_and(|selected_state| selected_state == state.id) {
+    if is_final {
+        ui.painter().circle_stroke(
+            state.position,
+            Constant::STATE_RADIUS - Constant::ACCEPTING_RING_GAP,
+            Stroke::new(2.0, constrast_color(color))
+        );
+    }
+
+    if is_initial {
+        draw_initial_arrow(ui, state.position);
+    }
+
+    // if there is a state edited and it's the same as this one
+    if is_selected && selection.is_editing {
 
         let response = ui.scope(|ui| {
             
@@ -122,7 +239,7 @@ fn draw_node(
 
             ui.put(
                 rect,
-                TextEdit::singleline(&mut state.name)
+                TextEdit::singleline(&mut selection.name_input)
                     .font(Constant::big_font())
                     .horizontal_align(Align::Center)
                     .vertical_align(Align::Center),
@@ -131,6 +248,17 @@ fn draw_node(
 
         response.request_focus();
 
+        // the name typed is kept until it is valid
+        if let Some(error) = &selection.rename_error {
+            ui.painter().text(
+                state.position + vec2(0.0, Constant::STATE_RADIUS + Constant::ACCEPTING_RING_GAP),
+                Align2::CENTER_TOP,
+                error,
+                Constant::default_font(),
+                Constant::NEGATIVE_COLOR,
+            );
+        }
+
         response
 
     } else {
@@ -146,4 +274,35 @@ fn draw_node(
 
         ui.allocate_rect(rect, Sense::click_and_drag())
     }
-}
\ No newline at end of file
+}
+
+/// Draw the arrow pointing to the initial state from its left
+fn draw_initial_arrow(ui: &mut Ui, position: Pos2) {
+    let (line, head) = initial_arrow(position);
+
+    ui.painter().line_segment(
+        line,
+        Stroke::new(Constant::TRANSITION_THICKNESS * 2.0, Constant::PRIMARY_COLOR)
+    );
+
+    ui.painter().add(PathShape::convex_polygon(
+        head.to_vec(),
+        Constant::PRIMARY_COLOR,
+        Stroke::NONE,
+    ));
+}
+
+/// The line and the head of the arrow pointing to the initial state, from its left
+pub fn initial_arrow(position: Pos2) -> ([Pos2; 2], [Pos2; 3]) {
+    let tip = position - vec2(Constant::STATE_RADIUS, 0.0);
+    let start = tip - vec2(Constant::INITIAL_ARROW_LENGTH, 0.0);
+
+    (
+        [start, tip],
+        [
+            tip,
+            tip - vec2(Constant::ARROW_SIZE, Constant::ARROW_SIZE / 2.0),
+            tip - vec2(Constant::ARROW_SIZE, -Constant::ARROW_SIZE / 2.0),
+        ],
+    )
+}
@@ -1,23 +1,34 @@
 use egui::{
-    epaint::{CubicBezierShape, PathShape, QuadraticBezierShape}, vec2, Align, Color32, CornerRadius, Label, Pos2, Rect, RichText, Sense, Stroke, StrokeKind, TextEdit, Ui, Vec2
+    epaint::{CubicBezierShape, PathShape, QuadraticBezierShape}, vec2, Color32, CornerRadius, Label, Pos2, Rect, RichText, Sense, Stroke, StrokeKind, Ui, Vec2
 };
 
 use crate::{
     app::Selection,
-    ui::{constant::Constant, turing::Transition, utils},
+    ui::{constant::Constant, turing::{CurveOffset, Transition}, utils},
 };
 
+use super::GraphEdit;
 
-/// Draw transitions between 2 different states with a quadractic bezier.
-pub fn draw_normal_transitions(
-    selection: &mut Selection,
-    ui: &mut Ui,
+
+/// The curve of the transitions between two states
+#[derive(Clone, Copy)]
+pub enum Curve {
+    /// A quadratic bezier between 2 different states
+    Normal([Pos2; 3]),
+    /// A cubic bezier from a state to itself
+    Loop([Pos2; 4]),
+}
+
+/// The curve between 2 different states and the center of its labels, before routing.
+/// The curve bends on the side away from the center of the graph.
+pub fn normal_curve(
+    ui: &Ui,
     source: Pos2,
     target: Pos2,
-    transitions: Vec<&mut Transition>,
+    transitions: &[&mut Transition],
     graph_center: Vec2,
-    reverse: bool
-) {
+    reverse: bool,
+) -> ([Pos2; 3], Pos2) {
     // the perpendicular vector to the vector between the position of the 2 states
     let mut delta = (source - target).rot90().normalized();
 
@@ -40,61 +51,18 @@ pub fn draw_normal_transitions(
         target
     ];
 
-    // draw the bezier
-    ui.painter().add(QuadraticBezierShape::from_points_stroke(
-        points,
-        false,
-        Color32::TRANSPARENT,
-        Stroke::new(Constant::TRANSITION_THICKNESS, Constant::PRIMARY_COLOR),
-    ));
-
-    let curve_lenght = get_quadratic_len(points, 100);
-    let arrow_position = quadraticbeziercurve(points, map(&curve_lenght, 100, 1.0 - Constant::STATE_RADIUS/curve_lenght.last().unwrap()));
-    let arrow_direction = (arrow_position - target.to_vec2()).normalized();
-
-    // points of the triangle
-    let triangles = vec![
-        arrow_position.to_pos2(),
-        (arrow_position
-            + arrow_direction * Constant::ARROW_SIZE
-            + arrow_direction.rot90() * Constant::ARROW_SIZE / 2.0)
-            .to_pos2(),
-        (arrow_position + arrow_direction * Constant::ARROW_SIZE
-            - arrow_direction.rot90() * Constant::ARROW_SIZE / 2.0)
-            .to_pos2(),
-    ];
-
-    // draw the triangle
-    ui.painter().add(PathShape::convex_polygon(
-        triangles,
-        Constant::PRIMARY_COLOR,
-        Stroke::NONE,
-    ));
-    
     let rules_len = transitions[0].text.len();
     let offset = vec2(
         Constant::TRANSITION_CURVATURE + rules_len as f32 * Constant::get_width(ui, &Constant::default_font()) / 2.0, 
         Constant::TRANSITION_CURVATURE + transitions.len() as f32 * (Constant::get_heigt(ui, &Constant::default_font()) - 10.0)
     );
 
-    draw_labels(
-        selection,
-        ui,
-        center.to_pos2(),
-        transitions,
-        (center + delta * offset).to_pos2(),
-    );
+    (points, (center + delta * offset).to_pos2())
 }
 
-
-/// Draw transitions between the same state with a cubic bezier.
-pub fn draw_loop_transitions(
-    selection: &mut Selection,
-    ui: &mut Ui,
-    source: Pos2,
-    transitions: Vec<&mut Transition>,
-    graph_center: Vec2,
-) {
+/// The curve from a state to itself and the center of its labels.
+/// The loop points away from the center of the graph.
+pub fn loop_curve(source: Pos2, graph_center: Vec2) -> ([Pos2; 4], Pos2) {
     // delta based on the center and the only state position
     let delta = (source.to_vec2() - graph_center).normalized();
 
@@ -108,21 +76,119 @@ pub fn draw_loop_transitions(
         source,
     ];
 
+    let text_position = vec2(source.x + delta.x * size/2.0, source.y + delta.y * size/2.0).to_pos2();
+
+    (points, text_position)
+}
+
+/// The size of the labels of the transitions, stacked one above the other
+pub fn labels_size(ui: &Ui, transitions: &[&mut Transition]) -> Vec2 {
+    let longest = transitions.iter().map(|transition| transition.text.len()).max().unwrap_or(0);
+
+    vec2(
+        (longest + 5) as f32 * Constant::get_width(ui, &Constant::default_font()),
+        transitions.len() as f32 * Constant::get_heigt(ui, &Constant::default_font()),
+    )
+}
+
+/// Draw the transitions along their curve with their labels centered on the position.
+/// The middle of the curve has a handle to bend it and the labels can be dragged,
+/// a double click on the handle reset the curve and the labels.
+/// Return the new offset of the curve if it has been changed.
+pub fn draw_curve_transitions(
+    selection: &mut Selection,
+    ui: &mut Ui,
+    curve: &Curve,
+    offset: CurveOffset,
+    transitions: Vec<&mut Transition>,
+    label_position: Pos2,
+    fired: Option<(u8, u8)>,
+    edits: &mut Vec<GraphEdit>,
+) -> Option<CurveOffset> {
+    let color = edge_color(&transitions, fired);
+
+    // the middle of the curve, and how much it moves when the control points move
+    let (source, middle, control_ratio) = match curve {
+        Curve::Normal(points) => {
+            draw_normal_curve(ui, *points, color);
+            (points[0], quadraticbeziercurve(*points, 0.5).to_pos2(), 0.5)
+        }
+        Curve::Loop(points) => {
+            draw_loop_curve(ui, *points, color);
+            (points[0], cubicbeziercurve(*points, 0.5).to_pos2(), 0.75)
+        }
+    };
+
+    let handle_id = ui.id().with(("curve_handle", transitions[0].parent_id, transitions[0].target_id));
+    let handle = ui.interact(
+        Rect::from_center_size(middle, Vec2::splat(Constant::HANDLE_RADIUS * 2.0)),
+        handle_id,
+        Sense::click_and_drag(),
+    );
+    let handle_color = if handle.hovered() || handle.dragged() { Constant::SELECTED } else { Constant::FOREGROUND };
+    ui.painter().circle(middle, Constant::HANDLE_RADIUS, handle_color, Stroke::NONE);
+
+    let label_delta = draw_labels(selection, ui, source, transitions, label_position, fired, edits);
+
+    if handle.double_clicked() {
+        Some(CurveOffset::default())
+    } else if handle.dragged() || label_delta != Vec2::ZERO {
+        Some(CurveOffset {
+            control: offset.control + handle.drag_delta() / control_ratio,
+            label: offset.label + label_delta,
+        })
+    } else {
+        None
+    }
+}
+
+
+/// Draw a quadratic bezier between 2 different states, with an arrow on the target.
+fn draw_normal_curve(ui: &mut Ui, points: [Pos2; 3], color: Color32) {
+    // draw the bezier
+    ui.painter().add(QuadraticBezierShape::from_points_stroke(
+        points,
+        false,
+        Color32::TRANSPARENT,
+        Stroke::new(Constant::TRANSITION_THICKNESS, color),
+    ));
+
+    draw_arrow(ui, arrow(&Curve::Normal(points)), color);
+}
+
+
+/// Draw a cubic bezier from a state to itself, with an arrow on the state.
+fn draw_loop_curve(ui: &mut Ui, points: [Pos2; 4], color: Color32) {
     // draw the bezier
     ui.painter().add(CubicBezierShape::from_points_stroke(
         points,
         false,
         Color32::TRANSPARENT,
-        Stroke::new(Constant::TRANSITION_THICKNESS, Constant::PRIMARY_COLOR),
+        Stroke::new(Constant::TRANSITION_THICKNESS, color),
     ));
 
+    draw_arrow(ui, arrow(&Curve::Loop(points)), color);
+}
+
+
+/// The triangle of the arrow at the end of the curve, on the border of the target state
+pub fn arrow(curve: &Curve) -> [Pos2; 3] {
     // we get the arrow position on the curve
-    let curve_lenght = get_cubic_len(points, 100);
-    let arrow_position = cubicbeziercurve(points, map(&curve_lenght, 100, 1.0 - Constant::STATE_RADIUS/curve_lenght.last().unwrap()));
-    let arrow_direction = (arrow_position - source.to_vec2()).normalized();
+    let (arrow_position, target) = match curve {
+        Curve::Normal(points) => {
+            let curve_lenght = get_quadratic_len(*points, 100);
+            let t = map(&curve_lenght, 100, 1.0 - Constant::STATE_RADIUS/curve_lenght.last().unwrap());
+            (quadraticbeziercurve(*points, t), points[2])
+        }
+        Curve::Loop(points) => {
+            let curve_lenght = get_cubic_len(*points, 100);
+            let t = map(&curve_lenght, 100, 1.0 - Constant::STATE_RADIUS/curve_lenght.last().unwrap());
+            (cubicbeziercurve(*points, t), points[0])
+        }
+    };
+    let arrow_direction = (arrow_position - target.to_vec2()).normalized();
 
-    // points of the triangle
-    let triangles = vec![
+    [
         arrow_position.to_pos2(),
         (arrow_position
             + arrow_direction * Constant::ARROW_SIZE
@@ -131,35 +197,49 @@ pub fn draw_loop_transitions(
         (arrow_position + arrow_direction * Constant::ARROW_SIZE
             - arrow_direction.rot90() * Constant::ARROW_SIZE / 2.0)
             .to_pos2(),
-    ];
+    ]
+}
+
 
-    // draw the triangle
+/// Draw the head of an arrow
+fn draw_arrow(ui: &mut Ui, triangle: [Pos2; 3], color: Color32) {
     ui.painter().add(PathShape::convex_polygon(
-        triangles,
-        Constant::PRIMARY_COLOR,
+        triangle.to_vec(),
+        color,
         Stroke::NONE,
     ));
+}
 
-    let text_position = vec2(source.x + delta.x * size/2.0, source.y + delta.y * size/2.0).to_pos2();
 
-    draw_labels(selection, ui, source, transitions, text_position);
+/// The color of the curve, highlighted if one of its transitions has just been fired
+fn edge_color(transitions: &[&mut Transition], fired: Option<(u8, u8)>) -> Color32 {
+    if transitions.iter().any(|transition| fired == Some((transition.parent_id, transition.id))) {
+        Constant::SELECTED
+    } else {
+        Constant::PRIMARY_COLOR
+    }
 }
 
 
 
-/// draw the transitions rules as superposed label
+/// draw the transitions rules as superposed label.
+/// A rule is selected with a click and edited with a double click.
+/// Return how much the labels have been dragged.
 fn draw_labels(
     mut selection: &mut Selection,
     ui: &mut Ui,
     source: Pos2,
     mut transitions: Vec<&mut Transition>,
     position: Pos2,
-) {
+    fired: Option<(u8, u8)>,
+    edits: &mut Vec<GraphEdit>,
+) -> Vec2 {
 
     let font_height = Constant::get_heigt(ui, &Constant::default_font());
     let height_used = transitions.len() as f32 * font_height;
     // enumerate the transition
     let mut i: usize = 0;
+    let mut drag_delta = Vec2::ZERO;
 
     ui.painter().circle(position, 2.0, Color32::CYAN, Stroke::NONE);
     for transition in transitions {
@@ -170,55 +250,46 @@ fn draw_labels(
             vec2((transition.text.len() + 5) as f32 * Constant::get_width(ui, &Constant::default_font()), Constant::get_heigt(ui, &Constant::default_font())),
         );
 
-        // draw the text or a single line text edit if selected, then return the rect used
-        let rect = if selection.selected_transition.is_some_and(|selected_transition| selected_transition == (transition.parent_id, transition.id)) {
-            
-            // scope to not affect style of other parts
-            let response = ui.scope(|ui| {
-                ui.visuals_mut().extreme_bg_color = Color32::TRANSPARENT;
-                ui.visuals_mut().widgets.active.fg_stroke = Stroke::new(1.0, utils::constrast_color(Constant::PRIMARY_COLOR));
-                ui.visuals_mut().selection.stroke = Stroke::NONE;
-
-                ui.put(max_rect, TextEdit::singleline(&mut transition.text)
-                    .font(Constant::default_font())
-                    .text_color(utils::constrast_color(Constant::BACKGROUND_2))
-                    .horizontal_align(Align::Center)
-                    .vertical_align(Align::Center)
-                )
-            }).inner;
+        let is_selected = selection.selected_transition == Some((transition.parent_id, transition.id));
 
-            response.request_focus();
+        let mut text = RichText::new(&transition.text)
+            .font(Constant::default_font())
+            .color(Constant::PRIMARY_COLOR);
 
-            response
+        // highlight the transition that has just been fired
+        if fired == Some((transition.parent_id, transition.id)) {
+            text = text.color(Constant::SELECTED);
+        }
 
-        } else {
-            let mut text = RichText::new(&transition.text)
-                .font(Constant::default_font())
-                .color(Constant::PRIMARY_COLOR);
-            
-            if selection
-                .selected_transition
-                .is_some_and(|selected_transition| {
-                    selected_transition == (transition.parent_id, transition.id)
-                }) {
-                text = text.color(Constant::SELECTED);
-            }
+        if is_selected {
+            text = text.color(Constant::SELECTED).underline();
+        }
 
-            // draw the text
-            ui.put(max_rect, Label::new(text).extend())
-        }.rect;
+        // draw the text, then return the rect used
+        let rect = ui.put(max_rect, Label::new(text).extend()).rect;
 
-        // add a click listener to the rectangle of the label/textedit
-        let response = ui.allocate_rect(rect, Sense::click());
+        // add a click listener to the rectangle of the label
+        let response = ui.allocate_rect(rect, Sense::click_and_drag());
+        drag_delta += response.drag_delta();
 
-        // if a transition rule is clicked, then we set it as selected
+        // if a transition rule is clicked, then we set it as selected, a double click open its editor
         if response.clicked() {
             selection.selected_transition = Some((transition.parent_id, transition.id));
-            selection.selected_state = None;
+            selection.selected_states.clear();
+            selection.is_editing = response.double_clicked();
         }
 
+        response.context_menu(|ui| {
+            if ui.button("Delete transition").clicked() {
+                edits.push(GraphEdit::DeleteTransition(transition.parent_id, transition.id));
+                ui.close_menu();
+            }
+        });
+
         i += 1;
     }
+
+    drag_delta
 }
 
 
@@ -82,7 +82,7 @@ fn ruban(app: &mut TuringApp, ui: &mut Ui, index: usize, available_width: f32) {
         };
 
         // fetch the current step
-        let current_step = &app.current_step;
+        let current_step = &app.history.current().step;
 
         // p is the current pointer of the ribbon
         // input is the current word in the ribbon
@@ -0,0 +1,189 @@
+use egui::{ComboBox, Context, Grid, RichText, TextEdit, Ui, Window};
+use turingrs::turing_state::{TuringDirection, TuringTransition};
+
+use crate::{TuringApp, app::machine};
+
+use super::{component::button, constant::Constant};
+
+/// The move of a head, as chosen in the editor
+#[derive(Clone, Copy, PartialEq)]
+enum Move {
+    Left,
+    Right,
+    None,
+}
+
+impl Move {
+    const ALL: [Move; 3] = [Move::Left, Move::Right, Move::None];
+
+    fn from_direction(direction: &TuringDirection) -> Self {
+        match direction {
+            TuringDirection::Left => Move::Left,
+            TuringDirection::Right => Move::Right,
+            TuringDirection::None => Move::None,
+        }
+    }
+
+    fn to_direction(self) -> TuringDirection {
+        match self {
+            Move::Left => TuringDirection::Left,
+            Move::Right => TuringDirection::Right,
+            Move::None => TuringDirection::None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Move::Left => "Left",
+            Move::Right => "Right",
+            Move::None => "None",
+        }
+    }
+}
+
+/// The fields of the transition being edited, kept between frames.
+/// The first ribbon is the read ribbon, it has no written character.
+pub struct TransitionEditor {
+    pub parent_id: u8,
+    pub id: u8,
+    read: Vec<String>,
+    write: Vec<String>,
+    moves: Vec<Move>,
+    error: Option<String>,
+}
+
+impl TransitionEditor {
+    /// Fill the fields with the transition edited
+    pub fn new(parent_id: u8, id: u8, transition: &TuringTransition) -> Self {
+        Self {
+            parent_id,
+            id,
+            read: transition.chars_read.iter().map(|c| c.to_string()).collect(),
+            write: transition.chars_write.iter().map(|(c, _)| c.to_string()).collect(),
+            moves: std::iter::once(&transition.move_read)
+                .chain(transition.chars_write.iter().map(|(_, direction)| direction))
+                .map(Move::from_direction)
+                .collect(),
+            error: None,
+        }
+    }
+
+    /// Build the transition from the fields, every field must hold a single character which can be written in the code
+    fn to_transition(&self) -> Result<TuringTransition, String> {
+        let single_char = |text: &String| {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => machine::validate_symbol(c).map(|_| c),
+                _ => Err("Each ribbon must read and write exactly one character".to_string()),
+            }
+        };
+
+        let chars_read = self.read.iter().map(single_char).collect::<Result<Vec<char>, String>>()?;
+        let chars_write = self.write.iter().map(single_char).collect::<Result<Vec<char>, String>>()?;
+
+        Ok(TuringTransition::new(
+            chars_read,
+            self.moves[0].to_direction(),
+            chars_write
+                .into_iter()
+                .zip(self.moves[1..].iter().map(|m| m.to_direction()))
+                .collect(),
+        ))
+    }
+}
+
+/// Display the editor of the selected transition in a window.
+/// Applying the edit replace the transition in the machine and in the graph.
+pub fn show(app: &mut TuringApp, ctx: &Context) {
+    let Some(editor) = &mut app.transition_editor else {
+        return;
+    };
+
+    let mut is_open = true;
+    let mut is_applied = false;
+    let mut is_cancelled = false;
+
+    Window::new("Transition")
+        .open(&mut is_open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            Grid::new("transition_editor_grid")
+                .num_columns(4)
+                .spacing((10.0, 5.0))
+                .show(ui, |ui| {
+                    ui.label("Ribbon");
+                    ui.label("Read");
+                    ui.label("Write");
+                    ui.label("Move");
+                    ui.end_row();
+
+                    for ribbon in 0..editor.read.len() {
+                        ui.label(if ribbon == 0 { "Input".to_string() } else { format!("Ribbon {}", ribbon) });
+
+                        char_field(ui, &mut editor.read[ribbon]);
+
+                        // the read ribbon is never written
+                        if ribbon == 0 {
+                            ui.label("");
+                        } else {
+                            char_field(ui, &mut editor.write[ribbon - 1]);
+                        }
+
+                        ComboBox::from_id_salt(("transition_editor_move", ribbon))
+                            .selected_text(editor.moves[ribbon].name())
+                            .show_ui(ui, |ui| {
+                                for m in Move::ALL {
+                                    ui.selectable_value(&mut editor.moves[ribbon], m, m.name());
+                                }
+                            });
+                        ui.end_row();
+                    }
+                });
+
+            if let Some(error) = &editor.error {
+                ui.label(RichText::new(error).color(Constant::NEGATIVE_COLOR));
+            }
+
+            ui.horizontal(|ui| {
+                let apply = button(ui.style_mut(), "Apply");
+                is_applied = ui.add(apply).clicked();
+
+                let cancel = button(ui.style_mut(), "Cancel");
+                is_cancelled = ui.add(cancel).clicked();
+            });
+        });
+
+    if is_applied {
+        let (parent_id, id) = (editor.parent_id, editor.id);
+        match editor.to_transition().and_then(|transition| app.replace_transition(parent_id, id, transition)) {
+            Ok(()) => {
+                app.graph_changed(ctx.input(|input| input.time));
+                close(app);
+            }
+            Err(error) => {
+                if let Some(editor) = &mut app.transition_editor {
+                    editor.error = Some(error);
+                }
+            }
+        }
+    } else if is_cancelled || !is_open {
+        close(app);
+    }
+}
+
+/// Close the editor, the transition stays selected
+fn close(app: &mut TuringApp) {
+    app.transition_editor = None;
+    app.selection.is_editing = false;
+}
+
+/// A field holding a single character of a ribbon
+fn char_field(ui: &mut Ui, text: &mut String) {
+    ui.add(
+        TextEdit::singleline(text)
+            .char_limit(1)
+            .desired_width(Constant::SQUARE_SIZE)
+            .font(Constant::code_font()),
+    );
+}
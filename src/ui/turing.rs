@@ -1,5 +1,8 @@
-use egui::{Color32, Pos2};
+use std::collections::HashMap;
+
+use egui::{Color32, Pos2, Vec2};
 use rand::random_range;
+use serde::{Deserialize, Serialize};
 
 use super::constant::Constant;
 
@@ -11,6 +14,21 @@ pub struct State {
     pub position: Pos2,
     pub color: Color32,
     pub transitions: Vec<Transition>,
+    /// Speed of the state in the force simulation
+    pub velocity: Vec2,
+    /// A pinned state is not moved by the force simulation, as when it is dragged
+    pub is_pinned: bool,
+    /// The offsets of the curves from this state, by name of the target state
+    pub curves: HashMap<String, CurveOffset>,
+}
+
+/// The offsets chosen by the user for the transitions from a state to another
+#[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CurveOffset {
+    /// Offset of the middle control points of the curve
+    pub control: Vec2,
+    /// Offset of the labels, the labels moved are not placed automatically anymore
+    pub label: Vec2,
 }
 
 /// Transition graphical representation
@@ -30,7 +48,10 @@ impl State {
             name: name,
             position: position,
             color: Constant::PRIMARY_COLOR,
-            transitions: vec![]
+            transitions: vec![],
+            velocity: Vec2::ZERO,
+            is_pinned: false,
+            curves: HashMap::new(),
         }
     }
 
@@ -40,7 +61,10 @@ impl State {
             name: name,
             position: Pos2::new(random_range(0.0..1.0), random_range(0.0..1.0)),
             color: Constant::PRIMARY_COLOR,
-            transitions: vec![]
+            transitions: vec![],
+            velocity: Vec2::ZERO,
+            is_pinned: false,
+            curves: HashMap::new(),
         }
     }
 }
\ No newline at end of file